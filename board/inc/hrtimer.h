@@ -0,0 +1,26 @@
+/**
+    ******************************************************************************
+    * @file    hrtimer.h
+    * @brief   This file contains all the function prototypes for
+    *          the hrtimer.c file
+    ******************************************************************************
+    */
+#ifndef __HRTIMER_H__
+#define __HRTIMER_H__
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#include "board.h"
+
+// 与 kernel 中的 HIRES_HZ 保持一致
+#define HRTIMER_HZ 1000000U
+
+void hrtimer_init(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* __HRTIMER_H__ */
@@ -0,0 +1,34 @@
+/**
+    ******************************************************************************
+    * @file    isr.h
+    * @brief   This file contains the prototypes of the kernel functions
+    *          interrupt handlers use to hand work to Rust tasks
+    ******************************************************************************
+    */
+#ifndef __ISR_H__
+#define __ISR_H__
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#include <stdint.h>
+
+// 与 kernel 中的 MAX_ISR_EVENTS 保持一致
+#define ISR_MAX_EVENTS 32U
+
+// 以下函数可在中断中调用，成功返回 0，失败返回 -1
+int isr_post_event(uint32_t event);
+int isr_wake_task(uint32_t task_id);
+
+// kernel 中 EventGroup::as_handle 返回的句柄
+typedef struct event_group event_group_t;
+
+// 在中断中置位事件组的 bits，句柄为空时返回 -1
+int event_group_set_bits_from_isr(const event_group_t *group, uint32_t bits);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* __ISR_H__ */
@@ -0,0 +1,38 @@
+/**
+    ******************************************************************************
+    * @file    rtc.h
+    * @brief   This file contains all the function prototypes for
+    *          the rtc.c file
+    ******************************************************************************
+    */
+#ifndef __RTC_H__
+#define __RTC_H__
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#include "board.h"
+#include <stdint.h>
+
+// 与 kernel 中的 RtcTime 保持一致
+struct rtc_time {
+    uint16_t year;
+    uint8_t month;
+    uint8_t day;
+    uint8_t hour;
+    uint8_t minute;
+    uint8_t second;
+    uint8_t weekday;  // 1 为星期一，7 为星期日
+    uint16_t millis;
+};
+
+int rtc_init(void);
+int rtc_get(struct rtc_time *time);
+int rtc_set(const struct rtc_time *time);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* __RTC_H__ */
@@ -23,6 +23,16 @@ void disable_irq() {
     __disable_irq();
 }
 
+uint32_t irq_save(void) {
+    uint32_t primask = __get_PRIMASK();
+    __disable_irq();
+    return primask;
+}
+
+void irq_restore(uint32_t primask) {
+    __set_PRIMASK(primask);
+}
+
 void _putchar(char ch) {
     if (ch == '\n') {
         char t = '\r';
@@ -38,9 +48,10 @@ void _putchar(char ch) {
 }
 
 void enter_sleep_mode(void) {
-    // __disable_irq();
+    // 调用方已关闭中断，挂起的中断仍会唤醒 WFI
 
     // 设置 Sleep Mode，等待中断触发
-    SCB->SCR |= SCB_SCR_SLEEPONEXIT_Msk;  // 使能退出时进入睡眠
+    SCB->SCR &= ~SCB_SCR_SLEEPONEXIT_Msk;  // 中断返回后回到线程模式，不再自动睡眠
+    __DSB();
     __WFI();  // 等待中断，CPU 进入 Sleep 模式
 }
\ No newline at end of file
@@ -7,6 +7,7 @@
 #include "fmc.h"
 #include "bsp_sdram.h"
 #include "sdmmc.h"
+#include "hrtimer.h"
 #include "printf.h"
 #include <stdint.h>
 
@@ -51,6 +52,8 @@ int _cstartup(void) {
     goto board_init_failed;
 #endif
 
+    hrtimer_init();
+
     //call kernel_main(Rust code)
     extern void kernel_main(uint8_t*, uint32_t);
     kernel_main((uint8_t*)sdram_addr, sdram_size);
@@ -0,0 +1,62 @@
+#include "hrtimer.h"
+#include <stdint.h>
+
+// TIM2 是 32 位定时器，自由运行作为高精度时钟：
+// CC1 作为闹钟，CC2 固定在计数中点，与溢出中断一起提供半周期中断，
+// 由 kernel 扩展为 64 位计数
+void hrtimer_init(void) {
+    __HAL_RCC_TIM2_CLK_ENABLE();
+
+    // APB1 分频不为 1 时，定时器时钟是 PCLK1 的两倍
+    uint32_t tim_clk = HAL_RCC_GetPCLK1Freq();
+    if ((RCC->D2CFGR & RCC_D2CFGR_D2PPRE1) != RCC_D2CFGR_D2PPRE1_DIV1) {
+        tim_clk *= 2;
+    }
+
+    TIM2->CR1 = 0;
+    TIM2->PSC = tim_clk / HRTIMER_HZ - 1;
+    TIM2->ARR = 0xFFFFFFFF;
+    TIM2->CNT = 0;
+    TIM2->CCR2 = 0x80000000;
+    TIM2->EGR = TIM_EGR_UG;  // 立即装载预分频值
+    TIM2->SR = 0;
+    TIM2->DIER = TIM_DIER_UIE | TIM_DIER_CC2IE;
+
+    HAL_NVIC_SetPriority(TIM2_IRQn, 0, 0);
+    HAL_NVIC_EnableIRQ(TIM2_IRQn);
+
+    TIM2->CR1 = TIM_CR1_CEN;
+}
+
+uint32_t get_hires_ticks(void) {
+    return TIM2->CNT;
+}
+
+void set_hires_alarm(uint32_t ticks) {
+    TIM2->CCR1 = ticks;
+    TIM2->SR = ~TIM_SR_CC1IF;
+    TIM2->DIER |= TIM_DIER_CC1IE;
+}
+
+void cancel_hires_alarm(void) {
+    TIM2->DIER &= ~TIM_DIER_CC1IE;
+}
+
+void TIM2_IRQHandler(void) {
+    extern void hires_period_handler(void);
+    extern void hires_alarm_handler(void);
+
+    uint32_t sr = TIM2->SR & TIM2->DIER;
+    TIM2->SR = ~sr;
+
+    if (sr & TIM_SR_UIF) {
+        hires_period_handler();
+    }
+    if (sr & TIM_SR_CC2IF) {
+        hires_period_handler();
+    }
+    if (sr & TIM_SR_CC1IF) {
+        TIM2->DIER &= ~TIM_DIER_CC1IE;
+        hires_alarm_handler();
+    }
+}
@@ -0,0 +1,93 @@
+#include "rtc.h"
+
+// LSE 32.768 kHz: 异步分频 128，同步分频 256，得到 1 Hz 日历时钟
+#define RTC_ASYNC_PREDIV 127U
+#define RTC_SYNC_PREDIV  255U
+#define RTC_INIT_TIMEOUT 1000U  // ms
+
+static uint8_t to_bcd(uint8_t val) {
+    return (uint8_t)(((val / 10) << 4) | (val % 10));
+}
+
+static uint8_t from_bcd(uint32_t bcd) {
+    return (uint8_t)(((bcd >> 4) & 0xF) * 10 + (bcd & 0xF));
+}
+
+static int wait_flag(volatile uint32_t *reg, uint32_t flag) {
+    uint32_t start = HAL_GetTick();
+    while ((*reg & flag) == 0) {
+        if (HAL_GetTick() - start > RTC_INIT_TIMEOUT) {
+            return -1;
+        }
+    }
+    return 0;
+}
+
+// 启动 LSE 与 RTC。备份域在复位后保持，已运行的 RTC 不会被重新初始化
+// 返回 0 表示 RTC 可用，-1 表示 LSE 无法起振
+int rtc_init(void) {
+    PWR->CR1 |= PWR_CR1_DBP;
+
+    if ((RCC->BDCR & RCC_BDCR_RTCEN) == 0) {
+        RCC->BDCR |= RCC_BDCR_LSEON;
+        uint32_t start = HAL_GetTick();
+        while ((RCC->BDCR & RCC_BDCR_LSERDY) == 0) {
+            if (HAL_GetTick() - start > LSE_STARTUP_TIMEOUT) {
+                return -1;
+            }
+        }
+        RCC->BDCR = (RCC->BDCR & ~RCC_BDCR_RTCSEL) | RCC_BDCR_RTCSEL_0;
+        RCC->BDCR |= RCC_BDCR_RTCEN;
+    }
+    __HAL_RCC_RTC_CLK_ENABLE();
+    return 0;
+}
+
+// 日历从未设置时返回 -1
+int rtc_get(struct rtc_time *time) {
+    if ((RTC->ISR & RTC_ISR_INITS) == 0) {
+        return -1;
+    }
+
+    // 读 SSR/TR 会锁存 DR，保证三者属于同一时刻
+    uint32_t ssr = RTC->SSR;
+    uint32_t tr = RTC->TR;
+    uint32_t dr = RTC->DR;
+
+    time->year = 2000 + from_bcd(dr >> 16);
+    time->month = from_bcd((dr >> 8) & 0x1F);
+    time->day = from_bcd(dr & 0x3F);
+    time->hour = from_bcd((tr >> 16) & 0x3F);
+    time->minute = from_bcd((tr >> 8) & 0x7F);
+    time->second = from_bcd(tr & 0x7F);
+    time->weekday = (uint8_t)((dr >> 13) & 0x7);
+    time->millis = (uint16_t)((RTC_SYNC_PREDIV - (ssr & 0xFFFF)) * 1000 / (RTC_SYNC_PREDIV + 1));
+    return 0;
+}
+
+int rtc_set(const struct rtc_time *time) {
+    if (time->year < 2000 || time->year > 2099) {
+        return -1;
+    }
+
+    RTC->WPR = 0xCA;
+    RTC->WPR = 0x53;
+
+    RTC->ISR |= RTC_ISR_INIT;
+    if (wait_flag(&RTC->ISR, RTC_ISR_INITF) != 0) {
+        RTC->ISR &= ~RTC_ISR_INIT;
+        RTC->WPR = 0xFF;
+        return -1;
+    }
+
+    RTC->PRER = (RTC_ASYNC_PREDIV << 16) | RTC_SYNC_PREDIV;
+    RTC->CR &= ~RTC_CR_FMT;  // 24 小时制
+    RTC->TR = ((uint32_t)to_bcd(time->hour) << 16) | ((uint32_t)to_bcd(time->minute) << 8) | to_bcd(time->second);
+    RTC->DR = ((uint32_t)to_bcd(time->year - 2000) << 16) | ((uint32_t)(time->weekday & 0x7) << 13) | ((uint32_t)to_bcd(time->month) << 8) | to_bcd(time->day);
+
+    RTC->ISR &= ~(RTC_ISR_INIT | RTC_ISR_RSF);
+    RTC->WPR = 0xFF;
+
+    // 等待影子寄存器同步，之后的读取才是新时间
+    return wait_flag(&RTC->ISR, RTC_ISR_RSF);
+}
@@ -0,0 +1,6 @@
+//! Runs the deterministic checks: `cargo run --features sim --target <host> --bin gungnir-check [name filter]`.
+
+fn main() {
+    let filter = std::env::args().nth(1);
+    kernel::check::run(filter.as_deref());
+}
@@ -0,0 +1,6 @@
+//! Runs the kernel on the host: `cargo run --features sim --target <host> --bin gungnir-sim [disk image]`.
+
+fn main() {
+    let disk_image = kernel::sim::disk_image_arg();
+    kernel::sim::boot(&disk_image);
+}
@@ -1,3 +1,18 @@
+/// Calendar fields exchanged with the board's RTC, `struct rtc_time` on the C side.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RtcTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// ISO weekday, 1 for Monday to 7 for Sunday
+    pub weekday: u8,
+    pub millis: u16,
+}
+
 #[allow(dead_code)]
 unsafe extern "C" {
     pub unsafe fn led_twinkle(ms: u32);
@@ -8,6 +23,11 @@ unsafe extern "C" {
 
     pub unsafe fn disable_irq();
 
+    /// Masks interrupts, returning whether they were masked already, for `irq_restore`.
+    pub unsafe fn irq_save() -> u32;
+
+    pub unsafe fn irq_restore(primask: u32);
+
     pub unsafe fn led_toggle();
 
     pub unsafe fn enter_sleep_mode();
@@ -17,4 +37,16 @@ unsafe extern "C" {
     pub unsafe fn sdmmc_write_blocks_it(data: *const u8, addr: u32, num: u32) -> i32;
 
     pub unsafe fn get_sdcard_capacity() -> u64;
+
+    pub unsafe fn get_hires_ticks() -> u32;
+
+    pub unsafe fn set_hires_alarm(ticks: u32);
+
+    pub unsafe fn cancel_hires_alarm();
+
+    pub unsafe fn rtc_init() -> i32;
+
+    pub unsafe fn rtc_get(time: *mut RtcTime) -> i32;
+
+    pub unsafe fn rtc_set(time: *const RtcTime) -> i32;
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+use core::pin::pin;
+
+use futures_util::future::{select, Either};
+
+use crate::{
+    cron::{self, MissedRuns, Schedule, TimeBase},
+    task::yield_now::yield_now,
+    time::{advance_sys_ticks_to, duration::Duration, get_sys_ticks, timer::Timer, virtual_clock::run_virtual},
+};
+
+pub(super) const CHECKS: &[super::Check] = &[
+    ("cron::clock_jump_counts_dropped_runs", clock_jump_counts_dropped_runs),
+];
+
+/// Runs past `MAX_CATCH_UP` that a clock jump drops still count as missed.
+fn clock_jump_counts_dropped_runs() {
+    let missed = run_virtual(async {
+        let check = async {
+            let job = cron::add("jump", Schedule::every(Duration::from_millis(1), Duration::from_ticks(0), TimeBase::Uptime), MissedRuns::Skip);
+            // let the service plan the first run, then stall the kernel for 10s
+            yield_now().await;
+            let stalled = get_sys_ticks();
+            advance_sys_ticks_to(stalled + 10_000);
+            Timer::after_millis(1).await;
+            let info = cron::jobs().into_iter().find(|info| info.id == job.id()).unwrap();
+            (info.missed, info.missed_more)
+        };
+        match select(pin!(cron::service()), pin!(check)).await {
+            Either::Left(_) => unreachable!(),
+            Either::Right((missed, _)) => missed,
+        }
+    }).unwrap();
+    assert_eq!(missed, (10_000, false));
+}
@@ -0,0 +1,487 @@
+use alloc::{boxed::Box, sync::Arc, task::Wake, vec::Vec};
+use core::{future::Future, pin::pin, ptr, task::{Context, Waker}};
+
+use futures_util::{future::{join, join3, join_all}, FutureExt};
+
+use crate::{
+    ipc::{
+        async_mutex::AsyncMutex,
+        async_signal::{AsyncSignal, WaitTimeoutErr},
+        barrier::Barrier,
+        broadcast::{self, RecvErr, TryRecvErr},
+        bus::{self, BusErr, Overflow, TryPublishErr},
+        channel::{self, channel, SendErr, SendTimeoutErr, TrySendErr},
+        event_group::{self, event_group_set_bits_from_isr, EventGroup, WaitFor},
+        notify::Notify,
+        rwlock::RwLock,
+        semaphore::Semaphore,
+        watch::{self, ChangedTimeoutErr},
+    },
+    task::yield_now::yield_now,
+    time::{duration::Duration, instant::Instant, timer::Timer, virtual_clock::run_virtual},
+};
+
+pub(super) const CHECKS: &[super::Check] = &[
+    ("ipc::broadcast_lagged", broadcast_lagged),
+    ("ipc::watch_changed", watch_changed),
+    ("ipc::channel_disconnect", channel_disconnect),
+    ("ipc::channel_send_waker", channel_send_waker),
+    ("ipc::event_group_any_all", event_group_any_all),
+    ("ipc::event_group_isr_bits", event_group_isr_bits),
+    ("ipc::bus_drop_oldest", bus_drop_oldest),
+    ("ipc::bus_backpressure", bus_backpressure),
+    ("ipc::bus_type_mismatch", bus_type_mismatch),
+    ("ipc::signal_wait_cancelled", signal_wait_cancelled),
+    ("ipc::mutex_fifo_handoff", mutex_fifo_handoff),
+    ("ipc::mutex_granted_lock_dropped", mutex_granted_lock_dropped),
+    ("ipc::rwlock_writer_fifo", rwlock_writer_fifo),
+    ("ipc::semaphore_cancelled_request", semaphore_cancelled_request),
+    ("ipc::notify_dropped_after_grant", notify_dropped_after_grant),
+    ("ipc::barrier_cancelled_waiter", barrier_cancelled_waiter),
+];
+
+fn ms_since(start: Instant) -> u64 {
+    (Instant::now() - start).as_millis()
+}
+
+struct NoopWake;
+
+impl Wake for NoopWake {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// A receiver that fell behind is told how many messages it lost, then reads on from the oldest kept.
+fn broadcast_lagged() {
+    let (tx, mut slow) = broadcast::broadcast::<u32, 4>();
+    let mut fast = tx.subscribe();
+    for msg in 0..3 {
+        assert_eq!(tx.send(msg), Ok(2));
+        assert_eq!(fast.try_recv(), Ok(msg));
+    }
+    for msg in 3..10 {
+        tx.send(msg).unwrap();
+    }
+
+    // only 6..10 are still kept
+    assert_eq!(slow.len(), 4);
+    assert_eq!(slow.try_recv(), Err(TryRecvErr::Lagged(6)));
+    assert_eq!(fast.try_recv(), Err(TryRecvErr::Lagged(3)));
+    for msg in 6..10 {
+        assert_eq!(slow.try_recv(), Ok(msg));
+    }
+    assert_eq!(slow.try_recv(), Err(TryRecvErr::Empty));
+
+    // a waiting receiver is woken by the next send, which pushes 6 out from under `fast`;
+    // both see the close once they read the rest
+    let (slow, fast) = run_virtual(async move {
+        let (slow, ()) = join(async { (slow.recv().await, slow.recv().await) }, async move {
+            Timer::after_millis(5).await;
+            tx.send(10).unwrap();
+        }).await;
+        let mut rest = Vec::new();
+        while rest.last() != Some(&Err(RecvErr::Closed)) {
+            rest.push(fast.recv().await);
+        }
+        (slow, rest)
+    }).unwrap();
+    assert_eq!(slow, (Ok(10), Err(RecvErr::Closed)));
+    assert_eq!(fast, [Err(RecvErr::Lagged(1)), Ok(7), Ok(8), Ok(9), Ok(10), Err(RecvErr::Closed)]);
+}
+
+/// `changed` wakes once for a burst of sends and yields the latest; `get` leaves it unseen.
+fn watch_changed() {
+    let (tx, mut rx) = watch::watch(0u32);
+    assert!(!rx.has_changed());
+    tx.send(1);
+    let late = tx.subscribe();
+    tx.send(2);
+
+    assert!(rx.has_changed());
+    assert_eq!(rx.get(), 2);
+    assert!(rx.has_changed());
+    assert_eq!(rx.get_and_update(), 2);
+    assert!(!rx.has_changed());
+    // subscribed at 1, so it has not seen 2
+    assert!(late.has_changed());
+
+    let steps = run_virtual(async move {
+        let start = Instant::now();
+        let timed_out = rx.changed_timeout(Duration::from_millis(10)).await;
+        let (changed, ()) = join(async {
+            let changed = rx.changed().await;
+            (changed, ms_since(start), rx.get_and_update())
+        }, async {
+            Timer::after_millis(15).await;
+            tx.send(3);
+            tx.send(4);
+        }).await;
+        drop(tx);
+        (timed_out, changed, rx.changed().await)
+    }).unwrap();
+    assert_eq!(steps, (Err(ChangedTimeoutErr::Timeout), (Ok(()), 25, 4), Err(watch::RecvErr)));
+}
+
+/// The receiver drains what was sent before the last sender left, then sees
+/// the channel closed; senders fail once the receiver closes or leaves.
+fn channel_disconnect() {
+    let received = run_virtual(async {
+        let (tx, rx) = channel::<u32, 4>();
+        let tx2 = tx.clone();
+        let (mut received, ()) = join(async {
+            let mut received = Vec::new();
+            while received.last() != Some(&Err(channel::RecvErr::Closed)) {
+                received.push(rx.recv().await);
+            }
+            received
+        }, async move {
+            tx.send(1).await.unwrap();
+            tx2.send(2).await.unwrap();
+            drop(tx);
+            Timer::after_millis(5).await;
+            tx2.send(3).await.unwrap();
+            tx2.send(4).await.unwrap();
+        }).await;
+        received.push(rx.recv().await);
+        received
+    }).unwrap();
+    assert_eq!(received, [Ok(1), Ok(2), Ok(3), Ok(4), Err(channel::RecvErr::Closed), Err(channel::RecvErr::Closed)]);
+
+    let (tx, rx) = channel::<u32, 4>();
+    assert!(!tx.is_closed());
+    rx.close();
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(5).now_or_never(), Some(Err(SendErr(5))));
+    assert_eq!(tx.try_send(6), Err(TrySendErr::Closed(6)));
+
+    // a send waiting for room fails when the receiver goes away
+    let res = run_virtual(async {
+        let (tx, rx) = channel::<u32, 1>();
+        tx.try_send(0).unwrap();
+        let (res, ()) = join(tx.send(1), async move {
+            Timer::after_millis(5).await;
+            drop(rx);
+        }).await;
+        (res, tx.try_send(2))
+    }).unwrap();
+    assert_eq!(res, (Err(SendErr(1)), Err(TrySendErr::Closed(2))));
+}
+
+/// A send waiting for room keeps one waker however often it is polled, and withdraws it when dropped.
+fn channel_send_waker() {
+    let (tx, rx) = channel::<u32, 1>();
+    tx.try_send(0).unwrap();
+    let wake = Arc::new(NoopWake);
+    let waker = Waker::from(wake.clone());
+    {
+        let mut send = pin!(tx.send(1));
+        for _ in 0..100 {
+            assert!(send.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        }
+        assert_eq!(Arc::strong_count(&wake), 3);
+    }
+    assert_eq!(Arc::strong_count(&wake), 2);
+
+    let res = run_virtual(async move {
+        for _ in 0..10 {
+            assert_eq!(tx.send_timeout(7, Duration::from_millis(1)).await, Err(SendTimeoutErr::Timeout(7)));
+        }
+        let (sent, received) = join(tx.send(2), async {
+            Timer::after_millis(5).await;
+            rx.recv().await
+        }).await;
+        (sent, received, rx.try_recv())
+    }).unwrap();
+    assert_eq!(res, (Ok(()), Ok(0), Ok(2)));
+}
+
+/// An any-wait ends on the first bit of its mask, an all-wait on the last,
+/// which then clears its mask.
+fn event_group_any_all() {
+    let res = run_virtual(async {
+        let start = Instant::now();
+        let group = EventGroup::new();
+        let (any, all, set) = join3(
+            async { (group.wait(0b011, WaitFor::Any, false).await, ms_since(start)) },
+            async { (group.wait(0b110, WaitFor::All, true).await, ms_since(start)) },
+            async {
+                let mut set = Vec::new();
+                for bits in [0b001, 0b100, 0b010] {
+                    Timer::after_millis(5).await;
+                    set.push(group.set(bits));
+                }
+                set
+            },
+        ).await;
+        let timed_out = group.wait_timeout(0b110, WaitFor::Any, false, Duration::from_millis(5)).await;
+        (any, all, set, timed_out)
+    }).unwrap();
+    assert_eq!(res.0, (0b001, 5));
+    assert_eq!(res.1, (0b111, 15));
+    // the last `set` returns the bits after the all-wait cleared its mask
+    assert_eq!(res.2, [0b001, 0b101, 0b001]);
+    assert_eq!(res.3, Err(event_group::WaitTimeoutErr(0b001)));
+}
+
+static ISR_GROUP: EventGroup = EventGroup::new();
+
+/// Bits set from an interrupt change at once and end the waits they satisfy on the executor's next pass.
+fn event_group_isr_bits() {
+    let (woken, set, after) = run_virtual(async {
+        let start = Instant::now();
+        let (woken, set) = join(async {
+            (ISR_GROUP.wait(0b1000, WaitFor::All, true).await, ms_since(start))
+        }, async {
+            Timer::after_millis(5).await;
+            assert_eq!(event_group_set_bits_from_isr(ISR_GROUP.as_handle(), 0b1000), 0);
+            let set = (ISR_GROUP.get(), event_group::has_isr_pending());
+            yield_now().await;
+            set
+        }).await;
+        (woken, set, ISR_GROUP.get())
+    }).unwrap();
+    assert_eq!(set, (0b1000, true));
+    assert_eq!(woken, (0b1000, 5));
+    assert_eq!(after, 0);
+    assert!(!event_group::has_isr_pending());
+    assert_eq!(event_group_set_bits_from_isr(ptr::null(), 0b1000), -1);
+}
+
+/// A full drop-oldest subscriber loses its oldest messages and counts them;
+/// publishers never wait for it.
+fn bus_drop_oldest() {
+    let tx = bus::publisher::<u32>("check/drop").unwrap();
+    let mut sub = bus::subscribe::<u32>("check/drop", 2, Overflow::DropOldest).unwrap();
+    for msg in 1..=5 {
+        assert_eq!(tx.try_publish(msg), Ok(1));
+    }
+    assert_eq!((sub.len(), sub.dropped()), (2, 3));
+    assert_eq!((sub.try_recv(), sub.try_recv(), sub.try_recv()), (Some(4), Some(5), None));
+
+    let info = bus::topics().into_iter().find(|info| info.name == "check/drop").unwrap();
+    assert_eq!((info.publishers, info.subscribers, info.published, info.dropped), (1, 1, 5, 3));
+}
+
+/// A full backpressure subscriber holds publishers back until it reads; a
+/// blocked publish keeps one waker however often it is polled.
+fn bus_backpressure() {
+    let tx = bus::publisher::<u32>("check/backpressure").unwrap();
+    let mut slow = bus::subscribe::<u32>("check/backpressure", 1, Overflow::Backpressure).unwrap();
+    let mut fast = bus::subscribe::<u32>("check/backpressure", 4, Overflow::DropOldest).unwrap();
+    assert_eq!(tx.try_publish(1), Ok(2));
+    assert_eq!(tx.try_publish(2), Err(TryPublishErr(2)));
+
+    let wake = Arc::new(NoopWake);
+    let waker = Waker::from(wake.clone());
+    {
+        let mut publish = pin!(tx.publish(2));
+        for _ in 0..100 {
+            assert!(publish.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+        }
+        assert_eq!(Arc::strong_count(&wake), 3);
+    }
+    assert_eq!(Arc::strong_count(&wake), 2);
+
+    let (published, received) = run_virtual(async move {
+        let start = Instant::now();
+        let published = async {
+            let delivered = tx.publish(2).await;
+            (delivered, ms_since(start))
+        };
+        let received = async {
+            Timer::after_millis(5).await;
+            let first = slow.recv().await;
+            (first, slow.recv().await, fast.try_recv(), fast.try_recv(), fast.try_recv())
+        };
+        join(published, received).await
+    }).unwrap();
+    assert_eq!(published, (2, 5));
+    assert_eq!(received, (1, 2, Some(1), Some(2), None));
+}
+
+/// A topic keeps the message type it was created with.
+fn bus_type_mismatch() {
+    let _tx = bus::publisher::<u32>("check/typed").unwrap();
+    assert!(bus::subscribe::<u32>("check/typed", 1, Overflow::DropOldest).is_ok());
+    assert_eq!(bus::subscribe::<u8>("check/typed", 1, Overflow::DropOldest).err(), Some(BusErr::TypeMismatch("u32")));
+    assert_eq!(bus::publisher::<i32>("check/typed").err(), Some(BusErr::TypeMismatch("u32")));
+}
+
+/// A `wait` that is dropped, e.g. by a timeout, leaves no waker behind.
+fn signal_wait_cancelled() {
+    let signal = AsyncSignal::<u32>::new();
+    let wake = Arc::new(NoopWake);
+    let waker = Waker::from(wake.clone());
+    for _ in 0..100 {
+        let wait = pin!(signal.wait());
+        assert!(wait.poll(&mut Context::from_waker(&waker)).is_pending());
+    }
+    // only our own `waker` still holds a reference
+    assert_eq!(Arc::strong_count(&wake), 2);
+
+    let (res, at) = run_virtual(async {
+        let signal = AsyncSignal::new();
+        for _ in 0..10 {
+            assert_eq!(signal.wait_timeout(Duration::from_millis(1)).await, Err(WaitTimeoutErr));
+        }
+        let start = Instant::now();
+        let (res, ()) = join(signal.wait_timeout(Duration::from_millis(50)), async {
+            Timer::after_millis(5).await;
+            signal.signal(7);
+        }).await;
+        (res, ms_since(start))
+    }).unwrap();
+    assert_eq!(res, Ok(7));
+    assert_eq!(at, 5);
+}
+
+/// The lock goes to waiters in the order they asked; one timing out just leaves the queue.
+fn mutex_fifo_handoff() {
+    let (order, gave_up) = run_virtual(async {
+        let start = Instant::now();
+        let mutex = AsyncMutex::new(Vec::new());
+        let locker = |id: u32, after: u64| {
+            let mutex = &mutex;
+            async move {
+                Timer::after_millis(after).await;
+                let mut guard = mutex.lock().await;
+                guard.push((id, ms_since(start)));
+                Timer::after_millis(5).await;
+                drop(guard);
+            }
+        };
+        let mut guard = mutex.lock().await;
+        let (_, gave_up, _) = join3(
+            async {
+                Timer::after_millis(5).await;
+                guard.push((0, ms_since(start)));
+                drop(guard);
+            },
+            async {
+                Timer::after_millis(1).await;
+                let res = mutex.lock_timeout(Duration::from_millis(2)).await;
+                (res.is_err(), ms_since(start))
+            },
+            join_all([locker(3, 4), locker(2, 3), locker(1, 2)]),
+        ).await;
+        let order = mutex.lock().await.clone();
+        (order, gave_up)
+    }).unwrap();
+    assert_eq!(gave_up, (true, 3));
+    assert_eq!(order, [(0, 5), (1, 5), (2, 10), (3, 15)]);
+}
+
+/// A waiter dropped after the lock was handed to it, before it ran, passes the lock on.
+fn mutex_granted_lock_dropped() {
+    let mutex = AsyncMutex::new(());
+    let guard = mutex.try_lock().unwrap();
+    let mut first = Box::pin(mutex.lock());
+    let mut second = Box::pin(mutex.lock());
+    assert!(first.as_mut().now_or_never().is_none());
+    assert!(second.as_mut().now_or_never().is_none());
+
+    drop(guard);
+    // handed to `first`, so nobody can barge in
+    assert!(mutex.try_lock().is_err());
+    drop(first);
+    let guard = second.as_mut().now_or_never().expect("lock not passed on");
+    drop(guard);
+    assert!(!mutex.is_locked());
+}
+
+/// A reader arriving after a waiting writer queues behind it.
+fn rwlock_writer_fifo() {
+    let (first, writer, late) = run_virtual(async {
+        let start = Instant::now();
+        let lock = RwLock::new(0);
+        join3(
+            async {
+                let guard = lock.read().await;
+                let at = ms_since(start);
+                Timer::after_millis(10).await;
+                drop(guard);
+                at
+            },
+            async {
+                Timer::after_millis(1).await;
+                let mut guard = lock.write().await;
+                *guard = 1;
+                let at = ms_since(start);
+                Timer::after_millis(10).await;
+                at
+            },
+            async {
+                Timer::after_millis(2).await;
+                let guard = lock.read().await;
+                (ms_since(start), *guard)
+            },
+        ).await
+    }).unwrap();
+    assert_eq!((first, writer, late), (0, 10, (20, 1)));
+}
+
+/// A large request timing out at the head of the queue lets the smaller one behind it through.
+fn semaphore_cancelled_request() {
+    let (large, small) = run_virtual(async {
+        let start = Instant::now();
+        let semaphore = Semaphore::new(3);
+        let held = semaphore.acquire().await;
+        let (_, large, small) = join3(
+            async {
+                Timer::after_millis(30).await;
+                drop(held);
+            },
+            async {
+                Timer::after_millis(1).await;
+                let res = semaphore.acquire_timeout(3, Duration::from_millis(10)).await;
+                (res.is_err(), ms_since(start))
+            },
+            async {
+                Timer::after_millis(2).await;
+                let _permit = semaphore.acquire().await;
+                ms_since(start)
+            },
+        ).await;
+        (large, small)
+    }).unwrap();
+    assert_eq!(large, (true, 11));
+    assert_eq!(small, 11);
+}
+
+/// A waiter dropped after `notify_one` picked it passes the notification on;
+/// one dropped after `notify_all` leaves nothing behind.
+fn notify_dropped_after_grant() {
+    let notify = Notify::new();
+    let mut first = Box::pin(notify.notified());
+    let mut second = Box::pin(notify.notified());
+    assert!(first.as_mut().now_or_never().is_none());
+    assert!(second.as_mut().now_or_never().is_none());
+
+    notify.notify_one();
+    drop(first);
+    assert!(second.as_mut().now_or_never().is_some());
+    // handed on, not stored as well
+    assert!(Box::pin(notify.notified()).as_mut().now_or_never().is_none());
+
+    let mut first = Box::pin(notify.notified());
+    let mut second = Box::pin(notify.notified());
+    assert!(first.as_mut().now_or_never().is_none());
+    assert!(second.as_mut().now_or_never().is_none());
+    notify.notify_all();
+    drop(first);
+    assert!(second.as_mut().now_or_never().is_some());
+    assert!(Box::pin(notify.notified()).as_mut().now_or_never().is_none());
+}
+
+/// A waiter leaving before the barrier opens takes its arrival back.
+fn barrier_cancelled_waiter() {
+    let barrier = Barrier::new(2);
+    let mut gone = Box::pin(barrier.wait());
+    assert!(gone.as_mut().now_or_never().is_none());
+    drop(gone);
+
+    let mut first = Box::pin(barrier.wait());
+    assert!(first.as_mut().now_or_never().is_none());
+    let last = Box::pin(barrier.wait()).as_mut().now_or_never().expect("barrier did not open");
+    assert!(last.is_leader());
+    assert!(!first.as_mut().now_or_never().expect("first waiter not released").is_leader());
+}
@@ -0,0 +1,37 @@
+//! Deterministic behaviour checks, run on the host by the `gungnir-check` binary.
+//!
+//! Each check drives kernel code through `run_virtual`, so timers fire at
+//! exact virtual instants and a check either passes or panics the same way
+//! on every run. The simulator is not booted, so its interrupt threads do
+//! not run and its TIM2 counter stands still: only the virtual clock, and
+//! whatever a check calls itself, moves either clock forward.
+
+extern crate std;
+
+mod cron;
+mod ipc;
+mod task;
+mod time;
+
+type Check = (&'static str, fn());
+
+const CHECKS: &[&[Check]] = &[
+    task::CHECKS,
+    cron::CHECKS,
+    ipc::CHECKS,
+    time::CHECKS,
+];
+
+/// Runs every check whose name contains `filter`, panicking at the first failure.
+pub fn run(filter: Option<&str>) {
+    let mut passed = 0;
+    for &(name, check) in CHECKS.iter().flat_map(|checks| checks.iter()) {
+        if filter.is_some_and(|filter| !name.contains(filter)) {
+            continue;
+        }
+        check();
+        std::println!("check {} ... ok", name);
+        passed += 1;
+    }
+    std::println!("{} checks passed", passed);
+}
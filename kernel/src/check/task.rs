@@ -0,0 +1,207 @@
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{cell::{Cell, RefCell}, future::poll_fn, task::{Poll, Waker}};
+
+use futures_util::future::{join, join_all};
+
+use crate::{
+    task::{executor::Executor, idle::IdleHook, run_queue::{QueueState, RunQueue, AGING_LIMIT}, scope::Scope, Priority, TaskId},
+    time::{instant::Instant, timer::Timer, virtual_clock::{run_virtual, VirtualClock}},
+};
+
+pub(super) const CHECKS: &[super::Check] = &[
+    ("task::priority_order", priority_order),
+    ("task::low_priority_ages", low_priority_ages),
+    ("task::rescan_skips_requeued_task", rescan_skips_requeued_task),
+    ("task::wake_storm", wake_storm),
+    ("task::scope_used_by_children", scope_used_by_children),
+];
+
+/// Tasks ready at the same time run highest priority first, whatever order they were spawned in.
+fn priority_order() {
+    let executor = Executor::with_idle_hook(Box::new(VirtualClock));
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let handles: Vec<_> = [Priority::Low, Priority::Normal, Priority::High].into_iter().map(|priority| {
+        let log = log.clone();
+        executor.spawn_with_priority("prio", async move {
+            log.borrow_mut().push(("spawned", priority));
+            // all three deadlines expire on the same tick
+            Timer::after_millis(10).await;
+            log.borrow_mut().push(("timer", priority));
+        }, priority)
+    }).collect();
+    executor.block_on(join_all(handles)).unwrap();
+
+    assert_eq!(log.take(), [
+        ("spawned", Priority::High),
+        ("spawned", Priority::Normal),
+        ("spawned", Priority::Low),
+        ("timer", Priority::High),
+        ("timer", Priority::Normal),
+        ("timer", Priority::Low),
+    ]);
+}
+
+/// A low-priority task gets its turn while a high-priority one keeps waking itself.
+fn low_priority_ages() {
+    const BUSY_POLLS: u32 = 10 * AGING_LIMIT;
+
+    let executor = Executor::with_idle_hook(Box::new(VirtualClock));
+    let busy_polls = Rc::new(Cell::new(0));
+    let low_ran_after = Rc::new(Cell::new(None));
+
+    let polls = low_ran_after.clone();
+    let low = executor.spawn_with_priority("low", {
+        let busy_polls = busy_polls.clone();
+        async move { polls.set(Some(busy_polls.get())) }
+    }, Priority::Low);
+    let polls = busy_polls.clone();
+    let high = executor.spawn_with_priority("busy", poll_fn(move |cx| {
+        polls.set(polls.get() + 1);
+        if polls.get() == BUSY_POLLS {
+            return Poll::Ready(());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }), Priority::High);
+    let (low, high) = executor.block_on(join(low, high)).unwrap();
+    low.unwrap();
+    high.unwrap();
+
+    let ran_after = low_ran_after.get().expect("low-priority task never ran");
+    assert!(ran_after > 0, "low-priority task ran ahead of a ready high-priority one");
+    assert!(ran_after <= AGING_LIMIT, "low-priority task waited {} rounds", ran_after);
+}
+
+fn rescan_skips_requeued_task() {
+    let queue = RunQueue::new(3);
+    let states: [QueueState; 5] = core::array::from_fn(|_| QueueState::new());
+    let schedule = |raw: u32| queue.schedule(TaskId::from_raw(raw), Priority::Normal, &states[raw as usize]);
+
+    // tasks 3 and 4 find the queue full
+    (0..5).for_each(schedule);
+    for _ in 0..3 {
+        let task_id = queue.pop().unwrap();
+        states[task_id.as_raw() as usize].clear();
+    }
+    assert!(queue.pop().is_none());
+
+    // an interrupt wakes task 0 after the drain, before the executor rescans
+    schedule(0);
+    assert!(queue.take_overflow());
+    for raw in 0..5 {
+        queue.requeue(TaskId::from_raw(raw), Priority::Normal, &states[raw as usize]);
+    }
+
+    let mut popped = Vec::new();
+    while let Some(task_id) = queue.pop() {
+        popped.push(task_id.as_raw());
+    }
+    assert_eq!(popped, [0, 3, 4]);
+    assert!(!queue.take_overflow());
+}
+
+const STORM_TASKS: usize = 300;
+const STORM_ROUNDS: u32 = 5;
+
+/// Stands in for an interrupt line: whenever the executor would sleep, it
+/// signals every task and wakes it twice, as back-to-back IRQs would.
+struct WakeStorm {
+    wakers: Rc<RefCell<Vec<Option<Waker>>>>,
+    signals: Rc<Vec<Cell<u32>>>,
+    rounds: Cell<u32>,
+}
+
+impl IdleHook for WakeStorm {
+    fn sleep(&self, _deadline: Option<Instant>) {
+        assert!(self.rounds.get() < STORM_ROUNDS, "a wakeup was lost: tasks still wait after the last round");
+        self.rounds.set(self.rounds.get() + 1);
+        let wakers = self.wakers.borrow();
+        for (signal, waker) in self.signals.iter().zip(wakers.iter()) {
+            signal.set(signal.get() + 1);
+            let waker = waker.as_ref().expect("task never registered its waker");
+            waker.wake_by_ref();
+            waker.wake_by_ref();
+        }
+    }
+}
+
+fn wake_storm() {
+    let wakers = Rc::new(RefCell::new(alloc::vec![None; STORM_TASKS]));
+    let signals: Rc<Vec<Cell<u32>>> = Rc::new((0..STORM_TASKS).map(|_| Cell::new(0)).collect());
+    let polls: Rc<Vec<Cell<u32>>> = Rc::new((0..STORM_TASKS).map(|_| Cell::new(0)).collect());
+    let executor = Executor::with_idle_hook(Box::new(WakeStorm {
+        wakers: wakers.clone(),
+        signals: signals.clone(),
+        rounds: Cell::new(0),
+    }));
+
+    let handles: Vec<_> = (0..STORM_TASKS).map(|i| {
+        let (wakers, signals, polls) = (wakers.clone(), signals.clone(), polls.clone());
+        executor.spawn_named("storm", poll_fn(move |cx| {
+            polls[i].set(polls[i].get() + 1);
+            if signals[i].get() == STORM_ROUNDS {
+                return Poll::Ready(());
+            }
+            wakers.borrow_mut()[i] = Some(cx.waker().clone());
+            Poll::Pending
+        }))
+    }).collect();
+    executor.block_on(join_all(handles)).unwrap();
+
+    // more tasks than the run queue holds, so the overflow rescan did run
+    assert!(executor.stat().queue_high_water as usize >= 100);
+    // one poll at spawn and one per round: no wakeup was dropped or doubled
+    for (i, polls) in polls.iter().enumerate() {
+        assert_eq!(polls.get(), STORM_ROUNDS + 1, "task {} polled {} times", i, polls.get());
+    }
+}
+
+/// Children borrow from the parent and, through an `Rc`, call back into their own scope.
+fn scope_used_by_children() {
+    let (res, log, ms) = run_virtual(async {
+        let start = Instant::now();
+        let log = RefCell::new(Vec::new());
+        let scope = Rc::new(Scope::<()>::new());
+        let (log_ref, child_scope) = (&log, scope.clone());
+        scope.spawn(async move {
+            log_ref.borrow_mut().push(("sees", child_scope.len()));
+            Timer::after_millis(100).await;
+            log_ref.borrow_mut().push(("outlived cancel", 0));
+            Ok(())
+        });
+        let child_scope = scope.clone();
+        scope.spawn(async move {
+            Timer::after_millis(5).await;
+            let grandchild_scope = child_scope.clone();
+            child_scope.spawn(async move {
+                log_ref.borrow_mut().push(("spawned by child", grandchild_scope.len()));
+                Ok(())
+            });
+            Ok(())
+        });
+        let child_scope = scope.clone();
+        scope.spawn(async move {
+            Timer::after_millis(20).await;
+            child_scope.cancel();
+            log_ref.borrow_mut().push(("cancelled", child_scope.len()));
+            Timer::after_millis(1).await;
+            log_ref.borrow_mut().push(("outlived cancel", 0));
+            Ok(())
+        });
+        let res = scope.join().await;
+        (res, log.take(), (Instant::now() - start).as_millis())
+    }).unwrap();
+    assert_eq!(res, Ok(()));
+    // the cancel drops the running children with it, the caller included
+    assert_eq!(log, [("sees", 3), ("spawned by child", 3), ("cancelled", 0)]);
+    assert_eq!(ms, 20);
+
+    let res = run_virtual(async {
+        let scope = Rc::new(Scope::new());
+        let child_scope = scope.clone();
+        scope.spawn(async { Timer::after_millis(50).await; Ok(()) });
+        scope.spawn(async move { Timer::after_millis(10).await; Err(child_scope.len()) });
+        scope.join().await.map_err(|running| (running, scope.len()))
+    }).unwrap();
+    assert_eq!(res, Err((2, 0)));
+}
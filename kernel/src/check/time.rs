@@ -0,0 +1,185 @@
+use alloc::{boxed::Box, rc::Rc, vec, vec::Vec};
+use core::cell::RefCell;
+
+use futures_util::{future::join_all, FutureExt, StreamExt};
+
+use crate::{
+    task::{executor::Executor, yield_now::yield_now},
+    time::{
+        advance_sys_ticks_to,
+        duration::Duration,
+        get_sys_ticks,
+        hires::{HiResInstant, HiResTimer},
+        instant::Instant,
+        timer::{with_timeout, Ticker, Timer, TimeoutError},
+        timer_queue::TIMER_QUEUE,
+        virtual_clock::{run_virtual, VirtualClock},
+    },
+};
+
+pub(super) const CHECKS: &[super::Check] = &[
+    ("time::timer_fires_at_deadline", timer_fires_at_deadline),
+    ("time::with_timeout", with_timeout_virtual),
+    ("time::virtual_runs_repeat", virtual_runs_repeat),
+    ("time::hires_follows_virtual_clock", hires_follows_virtual_clock),
+    ("time::ticker_leaves_no_entry", ticker_leaves_no_entry),
+    ("time::ticker_zero_period", ticker_zero_period),
+    // moves the clock past 2^32 ticks for good, so it stays last
+    ("time::ticks_cross_u32", ticks_cross_u32),
+];
+
+fn ms_since(start: Instant) -> u64 {
+    (Instant::now() - start).as_millis()
+}
+
+fn timer_fires_at_deadline() {
+    let fired = run_virtual(async {
+        let start = Instant::now();
+        Timer::after(Duration::from_millis(250)).await;
+        let first = ms_since(start);
+        Timer::after_secs(3).await;
+        (first, ms_since(start))
+    }).unwrap();
+    assert_eq!(fired, (250, 3_250));
+
+    // timers armed together fire in deadline order, each exactly on time
+    let order = run_virtual(async {
+        let start = Instant::now();
+        let woken = Rc::new(RefCell::new(Vec::new()));
+        join_all([30, 10, 20].map(|ms| {
+            let woken = woken.clone();
+            async move {
+                Timer::after_millis(ms).await;
+                woken.borrow_mut().push((ms, ms_since(start)));
+            }
+        })).await;
+        woken.take()
+    }).unwrap();
+    assert_eq!(order, vec![(10, 10), (20, 20), (30, 30)]);
+    assert_eq!(TIMER_QUEUE.lock().len(), 0);
+}
+
+fn with_timeout_virtual() {
+    let (timed_out, at) = run_virtual(async {
+        let start = Instant::now();
+        let res = with_timeout(Duration::from_millis(50), Timer::after_millis(100)).await;
+        (res, ms_since(start))
+    }).unwrap();
+    assert_eq!(timed_out, Err(TimeoutError));
+    assert_eq!(at, 50);
+
+    let (done, at) = run_virtual(async {
+        let start = Instant::now();
+        let res = with_timeout(Duration::from_millis(50), async {
+            Timer::after_millis(20).await;
+            7
+        }).await;
+        (res, ms_since(start))
+    }).unwrap();
+    assert_eq!(done, Ok(7));
+    assert_eq!(at, 20);
+    // the losing side's timer is gone from the queue either way
+    assert_eq!(TIMER_QUEUE.lock().len(), 0);
+}
+
+/// A few tasks mixing timers and yields, logging when each step ran.
+fn interleaving() -> Vec<(u32, u64)> {
+    let executor = Rc::new(Executor::with_idle_hook(Box::new(VirtualClock)));
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let start = Instant::now();
+    for id in 0..3u32 {
+        let log = log.clone();
+        executor.spawn_named("interleave", async move {
+            for step in 0..4u64 {
+                Timer::after_millis(5 * (id as u64 + 1) + step).await;
+                log.borrow_mut().push((id, ms_since(start)));
+                yield_now().await;
+            }
+        }).detach();
+    }
+    executor.block_on(Timer::after_millis(200)).unwrap();
+    log.take()
+}
+
+fn virtual_runs_repeat() {
+    let first = interleaving();
+    assert_eq!(first.len(), 12);
+    assert_eq!(first, interleaving());
+}
+
+/// The high-resolution clock moves only with the virtual clock, in step with the system clock.
+fn hires_follows_virtual_clock() {
+    let steps = run_virtual(async {
+        // a system timer leaves both clocks on the same tick boundary
+        Timer::after_millis(1).await;
+        let (sys, hires) = (Instant::now(), HiResInstant::now());
+        let elapsed = move || ((Instant::now() - sys).as_millis(), (HiResInstant::now() - hires).as_micros());
+
+        HiResTimer::after_micros(1_500).await;
+        let first = elapsed();
+        Timer::after_millis(2).await;
+        let second = elapsed();
+        HiResTimer::after_micros(250).await;
+        (first, second, elapsed())
+    }).unwrap();
+    assert_eq!(steps, ((1, 1_500), (3, 3_000), (3, 3_250)));
+}
+
+/// A tick taken before the timer queue saw its deadline pass leaves no entry behind.
+fn ticker_leaves_no_entry() {
+    let mut ticker = Ticker::every(Duration::from_millis(5));
+    assert!(ticker.next().now_or_never().is_none());
+    assert_eq!(TIMER_QUEUE.lock().len(), 1);
+
+    advance_sys_ticks_to(get_sys_ticks() + 5);
+    assert!(ticker.next().now_or_never().is_some());
+    assert_eq!(TIMER_QUEUE.lock().len(), 0);
+}
+
+/// A zero period, e.g. parsed from user input, ticks every tick instead of panicking.
+fn ticker_zero_period() {
+    let ticks = run_virtual(async {
+        let start = Instant::now();
+        let mut ticker = Ticker::every(Duration::from_ticks(0));
+        let mut ticks = Vec::new();
+        for _ in 0..3 {
+            ticker.next().await;
+            ticks.push(ms_since(start));
+        }
+        ticks
+    }).unwrap();
+    assert_eq!(ticks, [1, 2, 3]);
+}
+
+fn ticks_cross_u32() {
+    let wrap = 1u64 << 32;
+    advance_sys_ticks_to(wrap - 5);
+    assert_eq!(get_sys_ticks(), wrap - 5, "clock already past the 32-bit wrap");
+    assert!(Instant::from_ticks(wrap - 1) < Instant::from_ticks(wrap));
+
+    // ticks relative to the wrap, so the ones past it come out positive
+    let since_wrap = move || Instant::now().as_ticks() as i64 - wrap as i64;
+    let (ticks, timers) = run_virtual(async move {
+        let start = Instant::now();
+        let ticker = async {
+            let mut ticker = Ticker::every(Duration::from_millis(3));
+            let mut ticks = Vec::new();
+            for _ in 0..4 {
+                ticker.next().await;
+                ticks.push(since_wrap());
+            }
+            ticks
+        };
+        let timers = async {
+            let res = with_timeout(Duration::from_millis(8), Timer::after_millis(10)).await;
+            let timed_out = since_wrap();
+            Timer::at(start + Duration::from_millis(10)).await;
+            assert!(Instant::now() > start);
+            (res, timed_out, since_wrap())
+        };
+        futures_util::future::join(ticker, timers).await
+    }).unwrap();
+    assert_eq!(ticks, [-2, 1, 4, 7]);
+    assert_eq!(timers, (Err(TimeoutError), 3, 5));
+    assert_eq!(TIMER_QUEUE.lock().len(), 0);
+}
@@ -0,0 +1,168 @@
+use core::fmt;
+
+use alloc::string::{String, ToString};
+
+use crate::time::system_time::{CalendarTime, SystemTime};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// How far ahead `next_after` looks before giving up, e.g. on `0 0 30 2 *`.
+const SEARCH_YEARS: u64 = 5;
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParseCronError(&'static str);
+
+impl fmt::Display for ParseCronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// A cron expression, matched against the UTC wall clock.
+///
+/// Either the classic five fields `minute hour day-of-month month day-of-week`,
+/// or six with seconds in front. Fields take `*`, values, ranges `a-b`, steps
+/// `*/n` or `a-b/n` and comma lists; months and weekdays also take names
+/// (`jan`, `mon`), and weekday 7 is Sunday like 0. As in cron, when both day
+/// fields are restricted a day matching either one matches. `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` and `@yearly` are shorthands.
+#[derive(Debug, Clone)]
+pub(crate) struct CronExpr {
+    seconds: u64,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+    source: String,
+}
+
+impl CronExpr {
+    pub(crate) fn parse(source: &str) -> Result<Self, ParseCronError> {
+        let expanded = match source.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let mut fields = [""; 6];
+        let mut count = 0;
+        for field in expanded.split_whitespace() {
+            if count == fields.len() {
+                return Err(ParseCronError("expected 5 or 6 fields"));
+            }
+            fields[count] = field;
+            count += 1;
+        }
+        let (seconds, rest) = match count {
+            5 => ("0", &fields[..5]),
+            6 => (fields[0], &fields[1..]),
+            _ => return Err(ParseCronError("expected 5 or 6 fields")),
+        };
+
+        let weekdays = parse_field(rest[4], 0, 7, &WEEKDAY_NAMES)?;
+        Ok(Self {
+            seconds: parse_field(seconds, 0, 59, &[])?,
+            minutes: parse_field(rest[0], 0, 59, &[])?,
+            hours: parse_field(rest[1], 0, 23, &[])? as u32,
+            days: parse_field(rest[2], 1, 31, &[])? as u32,
+            months: parse_field(rest[3], 1, 12, &MONTH_NAMES)? as u16,
+            // fold 7 into 0, both are Sunday
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7F) as u8,
+            any_day: rest[2].starts_with('*'),
+            any_weekday: rest[4].starts_with('*'),
+            source: source.trim().to_string(),
+        })
+    }
+
+    /// The first matching second strictly after `after` (Unix milliseconds), if any within a few years.
+    pub(crate) fn next_after(&self, after: u64) -> Option<u64> {
+        let mut secs = after / 1_000 + 1;
+        let limit = secs + SEARCH_YEARS * 366 * SECS_PER_DAY;
+
+        while secs < limit {
+            let time = SystemTime::from_unix_secs(secs).to_calendar();
+            if self.months & (1 << time.month) == 0 {
+                secs = start_of_next_month(&time)?;
+            } else if !self.day_matches(&time) {
+                secs = secs - secs % SECS_PER_DAY + SECS_PER_DAY;
+            } else if self.hours & (1 << time.hour) == 0 {
+                secs = secs - secs % 3_600 + 3_600;
+            } else if self.minutes & (1 << time.minute) == 0 {
+                secs = secs - secs % 60 + 60;
+            } else if self.seconds & (1 << time.second) == 0 {
+                secs += 1;
+            } else {
+                return Some(secs * 1_000);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, time: &CalendarTime) -> bool {
+        let day = self.days & (1 << time.day) != 0;
+        let weekday = self.weekdays & (1 << (time.weekday() % 7)) != 0;
+        if !self.any_day && !self.any_weekday {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl fmt::Display for CronExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.source)
+    }
+}
+
+fn start_of_next_month(time: &CalendarTime) -> Option<u64> {
+    let (year, month) = if time.month == 12 { (time.year + 1, 1) } else { (time.year, time.month + 1) };
+    CalendarTime::new(year, month, 1, 0, 0, 0, 0).map(|start| SystemTime::from(start).as_unix_secs())
+}
+
+/// Parses one field into a bit set of the values in `min..=max`.
+fn parse_field(field: &str, min: u8, max: u8, names: &[&str]) -> Result<u64, ParseCronError> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u8>().ok().filter(|step| *step > 0).ok_or(ParseCronError("invalid step"))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, names)?, parse_value(end, min, names)?)
+        } else {
+            let start = parse_value(range, min, names)?;
+            // `a/n` runs from a to the end of the range
+            (start, if step.is_some() { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(ParseCronError("value out of range"));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(value: &str, min: u8, names: &[&str]) -> Result<u8, ParseCronError> {
+    if let Some(index) = names.iter().position(|name| name.eq_ignore_ascii_case(value)) {
+        return Ok(index as u8 + min);
+    }
+    value.parse().map_err(|_| ParseCronError("invalid value"))
+}
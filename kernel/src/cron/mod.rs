@@ -0,0 +1,419 @@
+//! Calendar and periodic job scheduling.
+//!
+//! A task registers a job with `add` and then consumes its runs as a
+//! stream, doing the work itself. The `service` task keeps every job's next
+//! run time and signals the job when it is due; runs that were missed (the
+//! job was still busy, the kernel was stalled, or the wall clock jumped
+//! forward) are handled by the job's `MissedRuns` policy.
+//!
+//! ```ignore
+//! let mut job = cron::add("log", Schedule::cron("0 */15 * * * *")?, MissedRuns::CatchUpOnce);
+//! while job.next().await.is_some() {
+//!     log_sample().await;
+//! }
+//! ```
+
+#![allow(dead_code)]
+
+use core::{fmt, future::poll_fn, pin::{pin, Pin}, sync::atomic::{AtomicBool, Ordering}, task::{Context, Poll, Waker}};
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use futures_core::Stream;
+use futures_util::{future::select, task::AtomicWaker};
+use spin::Mutex;
+
+use crate::time::{duration::Duration, instant::Instant, rtc, timer::Timer};
+
+pub(crate) mod expr;
+
+pub(crate) use expr::{CronExpr, ParseCronError};
+
+/// A run later than this after its scheduled time counts as missed.
+const MISS_TOLERANCE_MS: u64 = 1_000;
+/// Missed runs replayed at most per job and check; older ones are dropped.
+const MAX_CATCH_UP: u32 = 1_024;
+/// Dropped runs of a cron expression counted at most per check, one by one.
+const MAX_DROPPED_COUNT: u64 = 65_536;
+/// Wall-clock jobs are re-checked at least this often, since the clock can be set at any time.
+const WALL_CLOCK_RECHECK_MS: u64 = 1_000;
+
+static SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler::new());
+static SERVICE_WAKER: AtomicWaker = AtomicWaker::new();
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// The timeline a schedule is evaluated on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TimeBase {
+    /// Time since boot; always available and never jumps.
+    Uptime,
+    /// UTC wall clock; runs are held while it is unset.
+    WallClock,
+}
+
+impl TimeBase {
+    /// Now on this timeline, in milliseconds.
+    fn now(self) -> Option<u64> {
+        match self {
+            TimeBase::Uptime => Some(Instant::now().as_millis()),
+            TimeBase::WallClock => rtc::now().map(|now| now.as_unix_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Schedule {
+    /// Matches of a cron expression on the wall clock.
+    Cron(CronExpr),
+    /// Every `period`, `offset` after each multiple of it; e.g. every hour at
+    /// 5 past on the wall clock.
+    Every { period: Duration, offset: Duration, base: TimeBase },
+}
+
+impl Schedule {
+    pub(crate) fn cron(expr: &str) -> Result<Self, ParseCronError> {
+        CronExpr::parse(expr).map(Schedule::Cron)
+    }
+
+    pub(crate) fn every(period: Duration, offset: Duration, base: TimeBase) -> Self {
+        assert!(period.as_millis() > 0, "cron period must be at least 1 ms");
+        Schedule::Every { period, offset, base }
+    }
+
+    fn base(&self) -> TimeBase {
+        match self {
+            Schedule::Cron(_) => TimeBase::WallClock,
+            Schedule::Every { base, .. } => *base,
+        }
+    }
+
+    /// The first run strictly after `after`, in milliseconds on the schedule's base.
+    fn next_after(&self, after: u64) -> Option<u64> {
+        match self {
+            Schedule::Cron(expr) => expr.next_after(after),
+            Schedule::Every { period, offset, .. } => {
+                let period = period.as_millis();
+                let offset = offset.as_millis() % period;
+                if after < offset {
+                    return Some(offset);
+                }
+                let elapsed = (after - offset) / period + 1;
+                elapsed.checked_mul(period)?.checked_add(offset)
+            }
+        }
+    }
+
+    /// Runs from `from`, itself a run time, through `until`, and whether the
+    /// count is exact: a cron expression is walked for `MAX_DROPPED_COUNT`
+    /// runs at most.
+    fn runs_through(&self, from: u64, until: u64) -> (u64, bool) {
+        if from > until {
+            return (0, true);
+        }
+        match self {
+            Schedule::Every { period, .. } => ((until - from) / period.as_millis() + 1, true),
+            Schedule::Cron(expr) => {
+                let (mut runs, mut at) = (1, from);
+                while runs < MAX_DROPPED_COUNT {
+                    match expr.next_after(at) {
+                        Some(next) if next <= until => (runs, at) = (runs + 1, next),
+                        _ => return (runs, true),
+                    }
+                }
+                (runs, false)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Cron(expr) => write!(f, "{}", expr),
+            Schedule::Every { period, offset, base } => {
+                let base = match base {
+                    TimeBase::Uptime => "uptime",
+                    TimeBase::WallClock => "wall",
+                };
+                write!(f, "every {}ms +{}ms ({})", period.as_millis(), offset.as_millis(), base)
+            }
+        }
+    }
+}
+
+/// What to do with runs whose time passed without the job being run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MissedRuns {
+    /// Drop them and wait for the next scheduled time.
+    Skip,
+    /// Run once as soon as possible, however many were missed.
+    CatchUpOnce,
+    /// Run once for each missed time, back to back.
+    CatchUpAll,
+}
+
+impl fmt::Display for MissedRuns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            MissedRuns::Skip => "skip",
+            MissedRuns::CatchUpOnce => "once",
+            MissedRuns::CatchUpAll => "all",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct JobId(u32);
+
+impl JobId {
+    pub(crate) fn as_raw(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+pub(crate) struct JobInfo {
+    pub(crate) id: JobId,
+    pub(crate) name: &'static str,
+    pub(crate) schedule: Schedule,
+    pub(crate) policy: MissedRuns,
+    /// Next run in milliseconds on the schedule's base, if one is known.
+    pub(crate) next: Option<u64>,
+    pub(crate) runs: u64,
+    pub(crate) missed: u64,
+    /// `missed` is a lower bound: a clock jump dropped more runs than were counted.
+    pub(crate) missed_more: bool,
+}
+
+struct JobState {
+    name: &'static str,
+    schedule: Schedule,
+    policy: MissedRuns,
+    next: Option<u64>,
+    /// Runs signalled but not yet taken by the job.
+    pending: u32,
+    runs: u64,
+    missed: u64,
+    missed_more: bool,
+    waker: Option<Waker>,
+}
+
+impl JobState {
+    /// Moves past every run due at `now`, returning when the job should be checked again.
+    fn advance(&mut self, now: Option<u64>, last_now: Option<u64>) -> Option<u64> {
+        let now = now?;
+        // the wall clock was set back: the planned run may be far off now
+        if last_now.is_some_and(|last| now < last) || self.next.is_none() {
+            self.next = self.schedule.next_after(now);
+        }
+
+        let mut late = 0u32;
+        while let Some(due) = self.next.filter(|due| *due <= now) {
+            if late == MAX_CATCH_UP {
+                // dropped whatever the policy, so counted here
+                let (dropped, exact) = self.schedule.runs_through(due, now);
+                self.missed += dropped;
+                self.missed_more |= !exact;
+                self.next = self.schedule.next_after(now);
+                break;
+            }
+            if now - due > MISS_TOLERANCE_MS {
+                late += 1;
+            } else {
+                self.signal(1);
+            }
+            self.next = self.schedule.next_after(due);
+        }
+
+        if late > 0 {
+            match self.policy {
+                MissedRuns::Skip => self.missed += late as u64,
+                MissedRuns::CatchUpOnce => {
+                    self.missed += late as u64 - 1;
+                    self.signal(1);
+                }
+                MissedRuns::CatchUpAll => self.signal(late),
+            }
+        }
+        self.next
+    }
+
+    fn signal(&mut self, runs: u32) {
+        match self.policy {
+            MissedRuns::CatchUpAll => self.pending += runs,
+            // runs coalesce while the job is still busy with the last one
+            _ => {
+                self.missed += (self.pending + runs - 1) as u64;
+                self.pending = 1;
+            }
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Scheduler {
+    jobs: BTreeMap<JobId, JobState>,
+    next_id: u32,
+    last_uptime: Option<u64>,
+    last_wall: Option<u64>,
+}
+
+impl Scheduler {
+    const fn new() -> Self {
+        Self {
+            jobs: BTreeMap::new(),
+            next_id: 0,
+            last_uptime: None,
+            last_wall: None,
+        }
+    }
+
+    /// Signals every due job, returning how long the service may sleep.
+    fn run_due(&mut self) -> Option<Duration> {
+        let uptime = TimeBase::Uptime.now();
+        let wall = TimeBase::WallClock.now();
+
+        let mut wait: Option<u64> = None;
+        let mut wall_jobs = false;
+        for job in self.jobs.values_mut() {
+            let (now, last) = match job.schedule.base() {
+                TimeBase::Uptime => (uptime, self.last_uptime),
+                TimeBase::WallClock => {
+                    wall_jobs = true;
+                    (wall, self.last_wall)
+                }
+            };
+            if let (Some(next), Some(now)) = (job.advance(now, last), now) {
+                let until = next.saturating_sub(now);
+                wait = Some(wait.map_or(until, |wait| wait.min(until)));
+            }
+        }
+        if wall_jobs {
+            wait = Some(wait.map_or(WALL_CLOCK_RECHECK_MS, |wait| wait.min(WALL_CLOCK_RECHECK_MS)));
+        }
+
+        self.last_uptime = uptime;
+        self.last_wall = wall;
+        wait.map(Duration::from_millis)
+    }
+}
+
+/// A registered job; yields once per run, and ends once the job is removed.
+///
+/// Dropping it removes the job.
+pub(crate) struct Job {
+    id: JobId,
+}
+
+impl Job {
+    pub(crate) fn id(&self) -> JobId {
+        self.id
+    }
+}
+
+impl Stream for Job {
+    type Item = ();
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        let mut scheduler = SCHEDULER.lock();
+        let Some(job) = scheduler.jobs.get_mut(&self.id) else {
+            return Poll::Ready(None);
+        };
+        if job.pending > 0 {
+            job.pending -= 1;
+            job.runs += 1;
+            Poll::Ready(Some(()))
+        } else {
+            job.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        SCHEDULER.lock().jobs.remove(&self.id);
+    }
+}
+
+fn notify_service() {
+    CHANGED.store(true, Ordering::Release);
+    SERVICE_WAKER.wake();
+}
+
+/// Registers a job; its first run is the schedule's first time after now.
+pub(crate) fn add(name: &'static str, schedule: Schedule, policy: MissedRuns) -> Job {
+    let mut scheduler = SCHEDULER.lock();
+    let id = JobId(scheduler.next_id);
+    scheduler.next_id += 1;
+    scheduler.jobs.insert(id, JobState {
+        name,
+        schedule,
+        policy,
+        next: None,
+        pending: 0,
+        runs: 0,
+        missed: 0,
+        missed_more: false,
+        waker: None,
+    });
+    drop(scheduler);
+
+    notify_service();
+    Job { id }
+}
+
+/// Removes a job, ending its stream. Returns false if there was no such job.
+pub(crate) fn remove(id: JobId) -> bool {
+    let Some(job) = SCHEDULER.lock().jobs.remove(&id) else {
+        return false;
+    };
+    if let Some(waker) = job.waker {
+        waker.wake();
+    }
+    notify_service();
+    true
+}
+
+pub(crate) fn jobs() -> Vec<JobInfo> {
+    SCHEDULER.lock().jobs.iter().map(|(id, job)| JobInfo {
+        id: *id,
+        name: job.name,
+        schedule: job.schedule.clone(),
+        policy: job.policy,
+        next: job.next,
+        runs: job.runs,
+        missed: job.missed,
+        missed_more: job.missed_more,
+    }).collect()
+}
+
+/// The scheduler service; spawn it once.
+pub(crate) async fn service() {
+    loop {
+        CHANGED.store(false, Ordering::Release);
+        let wait = SCHEDULER.lock().run_due();
+
+        let changed = poll_fn(|cx| {
+            SERVICE_WAKER.register(cx.waker());
+            if CHANGED.load(Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        });
+        match wait {
+            Some(wait) => {
+                let timer = pin!(Timer::after(wait));
+                let _ = select(timer, pin!(changed)).await;
+            }
+            None => changed.await,
+        }
+    }
+}
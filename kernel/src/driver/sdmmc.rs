@@ -100,18 +100,20 @@ impl<const SIZE: usize> BlockDevice<SIZE> for SdmmcIo {
         for (i, buf) in data.iter_mut().enumerate() {
             let buf_ptr = buf[..].as_mut_ptr();
 
-            let res = self.read_blocks_it(buf_ptr, block_address + (i * num_block) as u32, num_block as u32);
-            if res != 0 {
-                error!("read_blocks_it return [{}]", res);
-                return Err(());
-            }
+            // registered before the transfer starts, as it may complete right away
             self.set_io_status(Self::IO_START);
-
             IO_REQS.lock().insert(
                 IoRequest::new(READ_REQUEST, buf_ptr as usize + SIZE),
                 (self.waker.clone(), self.io_status.clone())
             );
 
+            let res = self.read_blocks_it(buf_ptr, block_address + (i * num_block) as u32, num_block as u32);
+            if res != 0 {
+                error!("read_blocks_it return [{}]", res);
+                IO_REQS.lock().remove(&IoRequest::new(READ_REQUEST, buf_ptr as usize + SIZE));
+                return Err(());
+            }
+
             self.wait().await;
         }
         drop(guard);
@@ -134,18 +136,19 @@ impl<const SIZE: usize> BlockDevice<SIZE> for SdmmcIo {
         for (i, buf) in data.iter().enumerate() {
             let buf_ptr = buf[..].as_ptr();
 
-            let res = self.write_blocks_it(buf_ptr, block_address + (i * num_block) as u32, num_block as u32);
-            if res != 0 {
-                error!("write_blocks_it return [{}]", res);
-                return Err(())
-            }
             self.set_io_status(Self::IO_START);
-
             IO_REQS.lock().insert(
                 IoRequest::new(WRITE_REQUEST, buf_ptr as usize + SIZE),
                 (self.waker.clone(), self.io_status.clone())
             );
 
+            let res = self.write_blocks_it(buf_ptr, block_address + (i * num_block) as u32, num_block as u32);
+            if res != 0 {
+                error!("write_blocks_it return [{}]", res);
+                IO_REQS.lock().remove(&IoRequest::new(WRITE_REQUEST, buf_ptr as usize + SIZE));
+                return Err(())
+            }
+
             self.wait().await;
         }
         drop(guard);
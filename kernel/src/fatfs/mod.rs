@@ -21,7 +21,7 @@ pub(crate) mod fs;
 pub(crate) mod table;
 pub(crate) mod time;
 
-pub(crate) async fn fs_init(tx1: futures_channel::oneshot::Sender<()>, tx2: futures_channel::oneshot::Sender<()>) {
+pub(crate) async fn fs_init() {
     let sdmmc_io = SdmmcIo::new();
     let buf_stream = BufStream::<_, 512>::new(sdmmc_io);
 
@@ -34,13 +34,9 @@ pub(crate) async fn fs_init(tx1: futures_channel::oneshot::Sender<()>, tx2: futu
     } else {
         info!("fatfs already existed");
     }
-    tx1.send(()).unwrap();
-    tx2.send(()).unwrap();
 }
 
-pub(crate) async fn fs_test1(rx1: futures_channel::oneshot::Receiver<()>) {
-    rx1.await.expect("rx1 failed");
-
+pub(crate) async fn fs_test1() {
     let sdmmc_io = SdmmcIo::new();
     let buf_stream = BufStream::<_, 512>::new(sdmmc_io);
     let fs = FileSystem::new(buf_stream, FsOptions::new()).await.expect("create fatfs failed");
@@ -67,9 +63,7 @@ pub(crate) async fn fs_test1(rx1: futures_channel::oneshot::Receiver<()>) {
     info!("fs test 1 done");
 }
 
-pub(crate) async fn fs_test2(rx2: futures_channel::oneshot::Receiver<()>) {
-    rx2.await.expect("rx2 failed");
-
+pub(crate) async fn fs_test2() {
     let sdmmc_io = SdmmcIo::new();
     let buf_stream = BufStream::<_, 512>::new(sdmmc_io);
     let fs = FileSystem::new(buf_stream, FsOptions::new()).await.expect("create fatfs failed");
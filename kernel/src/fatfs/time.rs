@@ -4,6 +4,8 @@ use core::fmt::Debug;
 #[cfg(feature = "chrono")]
 use chrono::{Datelike, Local, TimeZone, Timelike};
 
+use crate::time::rtc;
+
 const MIN_YEAR: u16 = 1980;
 const MAX_YEAR: u16 = 2107;
 const MIN_MONTH: u16 = 1;
@@ -247,11 +249,49 @@ impl TimeProvider for NullTimeProvider {
     }
 }
 
+/// `TimeProvider` implementation that returns the kernel's wall-clock time (UTC).
+///
+/// Falls back to DOS minimal date-time while the wall clock is unset, and
+/// clamps to the DOS range [1980, 2107] otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RtcTimeProvider {
+    _dummy: (),
+}
+
+impl RtcTimeProvider {
+    #[must_use]
+    pub(crate) fn new() -> Self {
+        Self { _dummy: () }
+    }
+}
+
+impl TimeProvider for RtcTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        let Some(now) = rtc::now() else {
+            return DateTime::decode(0, 0, 0);
+        };
+        let now = now.to_calendar();
+        if now.year < MIN_YEAR {
+            return DateTime::decode(0, 0, 0);
+        }
+        if now.year > MAX_YEAR {
+            return DateTime::new(Date::new(MAX_YEAR, 12, 31), Time::new(23, 59, 59, 999));
+        }
+        let date = Date::new(now.year, u16::from(now.month), u16::from(now.day));
+        let time = Time::new(u16::from(now.hour), u16::from(now.minute), u16::from(now.second), now.millis);
+        DateTime::new(date, time)
+    }
+}
+
 /// Default time provider implementation.
 ///
-/// Defined as `ChronoTimeProvider` if `chrono` feature is enabled. Otherwise defined as `NullTimeProvider`.
+/// Defined as `ChronoTimeProvider` if `chrono` feature is enabled. Otherwise defined as `RtcTimeProvider`.
 #[cfg(feature = "chrono")]
 pub(crate) type DefaultTimeProvider = ChronoTimeProvider;
 #[cfg(not(feature = "chrono"))]
-pub(crate) type DefaultTimeProvider = NullTimeProvider;
+pub(crate) type DefaultTimeProvider = RtcTimeProvider;
 
@@ -0,0 +1,38 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use super::parse_duration_arg;
+use crate::{gsh::{register_cmd, CmdEntry}, ipc::bus, println};
+use crate::time::{duration::Duration, timer::Timer};
+
+const DEFAULT_SAMPLE_SECS: u64 = 1;
+
+async fn bus_func(sample: Duration) {
+    let before = bus::topics();
+    Timer::after(sample).await;
+    let after = bus::topics();
+
+    println!("{:<16} {:>4} {:>4} {:>10} {:>8} {:>8}", "TOPIC", "PUBS", "SUBS", "MSGS", "MSG/S", "DROPPED");
+    let millis = sample.as_millis().max(1);
+    for topic in after {
+        // topics created during the sample count from zero
+        let earlier = before.iter().find(|t| t.name == topic.name).map_or(0, |t| t.published);
+        let rate = (topic.published - earlier) * 1_000 / millis;
+        println!(
+            "{:<16} {:>4} {:>4} {:>10} {:>8} {:>8}",
+            topic.name, topic.publishers, topic.subscribers, topic.published, rate, topic.dropped,
+        );
+    }
+}
+
+fn bus_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    let sample = params.front()
+        .and_then(|arg| parse_duration_arg(arg).ok())
+        .filter(|sample| sample.as_ticks() > 0)
+        .unwrap_or(Duration::from_secs(DEFAULT_SAMPLE_SECS));
+    Box::pin(bus_func(sample))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("bus", CmdEntry::new("List message bus topics: bus [sample, e.g. 5s]", bus_func_wrapper));
+}
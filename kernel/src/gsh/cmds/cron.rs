@@ -0,0 +1,46 @@
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, string::String};
+use core::{future::Future, pin::Pin};
+use crate::{cron::{self, JobInfo, Schedule}, gsh::{register_cmd, CmdEntry}, print, println, time::system_time::SystemTime};
+
+fn print_next(job: &JobInfo) {
+    match (job.next, &job.schedule) {
+        (None, _) => println!("{:<20}", "-"),
+        (Some(next), Schedule::Cron(_)) => println!("{:<20}", SystemTime::from_unix_millis(next).to_calendar()),
+        (Some(next), Schedule::Every { base: cron::TimeBase::WallClock, .. }) => {
+            println!("{:<20}", SystemTime::from_unix_millis(next).to_calendar())
+        }
+        (Some(next), Schedule::Every { .. }) => println!("up {}.{:03}s", next / 1_000, next % 1_000),
+    }
+}
+
+async fn cron_func(params: VecDeque<String>) {
+    let mut params = params.into_iter();
+    match params.next().as_deref() {
+        None | Some("ls") => {
+            println!("{:<4} {:<12} {:<28} {:<5} {:>6} {:>6} {}", "ID", "NAME", "SCHEDULE", "MISS", "RUNS", "MISSED", "NEXT");
+            for job in cron::jobs() {
+                let schedule = alloc::format!("{}", job.schedule);
+                let missed = alloc::format!("{}{}", job.missed, if job.missed_more { "+" } else { "" });
+                print!("{:<4} {:<12} {:<28} {:<5} {:>6} {:>6} ", job.id, job.name, schedule, job.policy, job.runs, missed);
+                print_next(&job);
+            }
+        }
+        Some("rm") => {
+            let id = params.next().and_then(|id| id.parse::<u32>().ok());
+            let job = id.and_then(|id| cron::jobs().into_iter().find(|job| job.id.as_raw() == id));
+            match job {
+                Some(job) if cron::remove(job.id) => println!("removed job {} ({})", job.id, job.name),
+                _ => println!("cron: no such job"),
+            }
+        }
+        Some(other) => println!("cron: unknown argument {}", other),
+    }
+}
+
+fn cron_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(cron_func(params))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("cron", CmdEntry::new("List or remove scheduled jobs: cron [ls | rm <id>]", cron_func_wrapper));
+}
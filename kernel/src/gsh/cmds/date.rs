@@ -0,0 +1,33 @@
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, string::String, vec::Vec};
+use core::{future::Future, pin::Pin};
+use crate::{gsh::{register_cmd, CmdEntry}, println};
+use crate::time::{rtc, system_time::{CalendarTime, SystemTime}};
+
+async fn date_func(params: VecDeque<String>) {
+    let mut params = params.into_iter();
+    match params.next().as_deref() {
+        None => match rtc::now() {
+            Some(now) => println!("{} ({})", now, rtc::backend_name()),
+            None => println!("wall clock not set, use: date set YYYY-MM-DD HH:MM[:SS]"),
+        },
+        Some("set") => {
+            let time = params.collect::<Vec<_>>().join(" ");
+            match time.parse::<CalendarTime>() {
+                Ok(time) => match rtc::set(SystemTime::from(time)) {
+                    Ok(()) => println!("{} ({})", SystemTime::from(time), rtc::backend_name()),
+                    Err(err) => println!("date: cannot set {}: {:?}", time, err),
+                },
+                Err(err) => println!("date: {}", err),
+            }
+        }
+        Some(other) => println!("date: unknown argument {}", other),
+    }
+}
+
+fn date_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(date_func(params))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("date", CmdEntry::new("Show or set the UTC wall clock: date [set YYYY-MM-DD HH:MM[:SS]]", date_func_wrapper));
+}
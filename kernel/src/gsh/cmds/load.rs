@@ -0,0 +1,46 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use super::parse_duration_arg;
+use crate::{gsh::{executor, register_cmd, CmdEntry}, println};
+use crate::time::{duration::Duration, hires::HiResDuration, instant::Instant, timer::Timer};
+
+const DEFAULT_SAMPLE_SECS: u64 = 1;
+
+fn busy_percent(busy: HiResDuration, idle: HiResDuration) -> u64 {
+    (busy.as_ticks() * 100).checked_div((busy + idle).as_ticks()).unwrap_or(0)
+}
+
+async fn load_func(sample: Duration) {
+    let executor = executor();
+    let before = executor.stat();
+    Timer::after(sample).await;
+    let after = executor.stat();
+
+    let uptime = Instant::now().saturating_duration_since(after.since);
+    println!("{:<18} {}", "uptime", uptime);
+    println!("{:<18} {}", "polls", after.polls);
+    println!("{:<18} {}", "wakeups", after.wakeups);
+    println!("{:<18} {}", "queue high water", after.queue_high_water);
+    println!("{:<18} {}ms / {}ms", "busy / idle", after.busy.as_millis(), after.idle.as_millis());
+    println!("{:<18} {}%", "load (total)", busy_percent(after.busy, after.idle));
+    println!(
+        "{:<18} {}%, {} polls, {} wakeups",
+        "load (sample)",
+        busy_percent(after.busy - before.busy, after.idle - before.idle),
+        after.polls - before.polls,
+        after.wakeups.wrapping_sub(before.wakeups),
+    );
+}
+
+fn load_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    let sample = params.front()
+        .and_then(|arg| parse_duration_arg(arg).ok())
+        .filter(|sample| sample.as_ticks() > 0)
+        .unwrap_or(Duration::from_secs(DEFAULT_SAMPLE_SECS));
+    Box::pin(load_func(sample))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("load", CmdEntry::new("Show executor load: load [sample, e.g. 5s]", load_func_wrapper));
+}
@@ -1,9 +1,36 @@
 mod poem;
 mod uname;
 mod meminfo;
+mod ps;
+mod top;
+mod load;
+mod date;
+mod cron;
+mod uptime;
+mod sleep;
+mod time;
+mod bus;
+
+use crate::time::{duration::Duration, format::ParseDurationError};
 
 pub(super) fn add_cmds() {
     poem::add_cmd();
     uname::add_cmd();
     meminfo::add_cmd();
-}
\ No newline at end of file
+    ps::add_cmd();
+    top::add_cmd();
+    load::add_cmd();
+    date::add_cmd();
+    cron::add_cmd();
+    uptime::add_cmd();
+    sleep::add_cmd();
+    time::add_cmd();
+    bus::add_cmd();
+}
+/// Parses a duration argument such as `500ms` or `1h30m`; a bare number is seconds.
+pub(super) fn parse_duration_arg(arg: &str) -> Result<Duration, ParseDurationError> {
+    match arg.parse::<u64>() {
+        Ok(secs) => secs.checked_mul(1_000).map(Duration::from_millis).ok_or(ParseDurationError::Overflow),
+        Err(_) => arg.parse(),
+    }
+}
@@ -0,0 +1,25 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use crate::{gsh::{executor, register_cmd, CmdEntry}, print, println, time::instant::Instant};
+
+async fn ps_func() {
+    let now = Instant::now();
+    println!("{:<5} {:<12} {:<7} {:>8} {:>10} {:>8} {:>10}", "ID", "NAME", "PRIO", "POLLS", "BUSY(ms)", "AGE(s)", "WAKE(ms)");
+    for info in executor().task_infos() {
+        let age = now.saturating_duration_since(info.spawned_at);
+        print!("{:<5} {:<12} {:<7} {:>8} {:>10} {:>8} ", info.id, info.name, info.priority, info.polls, info.busy.as_millis(), age.as_secs());
+        match info.last_wake {
+            Some(last_wake) => println!("{:>10}", now.saturating_duration_since(last_wake).as_millis()),
+            None => println!("{:>10}", "-"),
+        }
+    }
+}
+
+fn ps_func_wrapper(_params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(ps_func())
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("ps", CmdEntry::new("List running tasks", ps_func_wrapper));
+}
@@ -0,0 +1,25 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use super::parse_duration_arg;
+use crate::{gsh::{register_cmd, CmdEntry}, println};
+use crate::time::timer::Timer;
+
+async fn sleep_func(params: VecDeque<String>) {
+    let Some(arg) = params.front() else {
+        println!("sleep: missing duration, e.g. sleep 1.5s");
+        return;
+    };
+    match parse_duration_arg(arg) {
+        Ok(duration) => Timer::after(duration).await,
+        Err(err) => println!("sleep: {}", err),
+    }
+}
+
+fn sleep_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(sleep_func(params))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("sleep", CmdEntry::new("Wait for a duration: sleep <duration, e.g. 1.5s>", sleep_func_wrapper));
+}
@@ -0,0 +1,26 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use crate::{gsh::{cmd_future, register_cmd, CmdEntry}, println};
+use crate::time::hires::HiResInstant;
+
+async fn time_func(mut params: VecDeque<String>) {
+    let Some(cmd) = params.pop_front() else {
+        println!("time: missing command, e.g. time sleep 1s");
+        return;
+    };
+    let Some(future) = cmd_future(&cmd, params) else {
+        return;
+    };
+    let start = HiResInstant::now();
+    future.await;
+    println!("real {}", HiResInstant::now() - start);
+}
+
+fn time_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(time_func(params))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("time", CmdEntry::new("Run a command and show how long it took: time <cmd> [args]", time_func_wrapper));
+}
@@ -0,0 +1,48 @@
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use super::parse_duration_arg;
+use crate::{gsh::{executor, register_cmd, CmdEntry}, print, println, task::TaskId};
+use crate::time::{duration::Duration, hires::{HiResDuration, HiResInstant}, timer::Timer};
+
+const DEFAULT_INTERVAL_SECS: u64 = 1;
+
+async fn top_func(interval: Duration) {
+    let executor = executor();
+    let mut last_busy: BTreeMap<TaskId, HiResDuration> = BTreeMap::new();
+    let mut last_time = HiResInstant::now();
+
+    loop {
+        let now = HiResInstant::now();
+        let window = now.saturating_duration_since(last_time).as_ticks().max(1);
+        let infos = executor.task_infos();
+
+        print!("\x1b[2J\x1b[H");
+        println!("{} tasks, refreshing every {}, Ctrl-C to quit\n", infos.len(), interval);
+        println!("{:<5} {:<12} {:<7} {:>8} {:>10} {:>6}", "ID", "NAME", "PRIO", "POLLS", "BUSY(ms)", "CPU%");
+        let mut busy = BTreeMap::new();
+        for info in infos {
+            let prev = last_busy.get(&info.id).copied().unwrap_or_default();
+            let delta = info.busy.checked_sub(prev).unwrap_or_default();
+            let cpu = delta.as_ticks() * 100 / window;
+            println!("{:<5} {:<12} {:<7} {:>8} {:>10} {:>6}", info.id, info.name, info.priority, info.polls, info.busy.as_millis(), cpu);
+            busy.insert(info.id, info.busy);
+        }
+
+        last_busy = busy;
+        last_time = now;
+        Timer::after(interval).await;
+    }
+}
+
+fn top_func_wrapper(params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    let interval = params.front()
+        .and_then(|arg| parse_duration_arg(arg).ok())
+        .filter(|interval| interval.as_ticks() > 0)
+        .unwrap_or(Duration::from_secs(DEFAULT_INTERVAL_SECS));
+    Box::pin(top_func(interval))
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("top", CmdEntry::new("Show tasks, refreshing: top [interval, e.g. 500ms]", top_func_wrapper));
+}
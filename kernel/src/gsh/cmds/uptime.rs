@@ -0,0 +1,16 @@
+use alloc::{collections::vec_deque::VecDeque, string::String, boxed::Box};
+use core::pin::Pin;
+use core::future::Future;
+use crate::{gsh::{register_cmd, CmdEntry}, println, time::instant::Instant};
+
+async fn uptime_func() {
+    println!("up {}", Instant::now());
+}
+
+fn uptime_func_wrapper(_params: VecDeque<String>) -> Pin<Box<dyn Future<Output = ()>>> {
+    Box::pin(uptime_func())
+}
+
+pub(super) fn add_cmd() {
+    register_cmd("uptime", CmdEntry::new("Show time since boot", uptime_func_wrapper));
+}
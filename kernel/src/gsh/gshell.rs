@@ -2,8 +2,14 @@ use core::future::Future;
 use core::pin::Pin;
 use alloc::{boxed::Box, collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::{String, ToString}, sync::Arc};
 use crate::println;
-use super::Executor;
-use futures_channel::oneshot;
+use super::{cmds::parse_duration_arg, Executor};
+use crate::task::join_handle::JoinHandle;
+use crate::time::timer::with_timeout;
+
+/// Flag any command accepts to be stopped after a duration, e.g. `load 10s --timeout 3s`.
+const TIMEOUT_FLAG: &str = "--timeout";
+
+type CmdFuture = Pin<Box<dyn Future<Output = ()>>>;
 
 
 #[derive(Clone)]
@@ -22,7 +28,7 @@ impl CmdEntry {
 }
 
 pub(super) struct GShell {
-    cmds: BTreeMap<String, CmdEntry>,
+    cmds: BTreeMap<&'static str, CmdEntry>,
     executor: Option<Arc<Executor>>,
 }
 
@@ -41,18 +47,22 @@ impl GShell {
         self.executor = Some(executor);
     }
 
+    pub(super) fn exec(&self) -> Option<Arc<Executor>> {
+        self.executor.clone()
+    }
+
     pub(super) fn add_cmd(&mut self, name: &'static str, cmd: CmdEntry) {
         match self.cmds.get(name) {
             Some(_) => {
                 println!("Command {} already exists", name);
             }
             None => {
-                self.cmds.insert(name.to_string(), cmd);
+                self.cmds.insert(name, cmd);
             }
         }
     }
 
-    pub(super) fn command(&self, line: &str, tx: oneshot::Sender<()>) {
+    pub(super) fn command(&self, line: &str) -> Option<JoinHandle<()>> {
         println!("\n");
         let mut words = split_to_words(line);
         let cmd = words.pop_front().expect("empty shell line");
@@ -61,26 +71,52 @@ impl GShell {
             for (name, entry) in self.cmds.iter() {
                 println!("{}: {}", name, entry.summary);
             }
-            tx.send(()).unwrap();
-            return;
+            return None;
+        }
+        let (name, future) = self.cmd_future(&cmd, params)?;
+        if let Some(executor) = &self.executor {
+            Some(executor.spawn_named(name, future))
+        } else {
+            println!("Executor not set");
+            None
         }
-        match self.cmds.get(&cmd) {
-            Some(entry) => {
-                let future_fn = entry.future_fn;
-                if let Some(executor) = &self.executor {
-                    executor.spawn(async move {
-                        future_fn(params).await;
-                        tx.send(()).unwrap();
-                    });
-                } else {
-                    println!("Executor not set");
-                    tx.send(()).unwrap();
+    }
+
+    /// The future running command `cmd`, stopped after the duration of a
+    /// `--timeout` flag among `params` if there is one. Complains and returns
+    /// `None` for an unknown command or a bad flag.
+    pub(super) fn cmd_future(&self, cmd: &str, mut params: VecDeque<String>) -> Option<(&'static str, CmdFuture)> {
+        let Some((name, entry)) = self.cmds.get_key_value(cmd) else {
+            println!("Command {} not found", cmd);
+            return None;
+        };
+        let name = *name;
+        let timeout = match params.iter().position(|param| param == TIMEOUT_FLAG) {
+            Some(at) => {
+                let Some(arg) = params.remove(at + 1) else {
+                    println!("{}: {} needs a duration, e.g. {} 5s", name, TIMEOUT_FLAG, TIMEOUT_FLAG);
+                    return None;
+                };
+                params.remove(at);
+                match parse_duration_arg(&arg) {
+                    Ok(timeout) => Some(timeout),
+                    Err(err) => {
+                        println!("{}: {} {}: {}", name, TIMEOUT_FLAG, arg, err);
+                        return None;
+                    }
                 }
             }
-            None => {
-                println!("Command {} not found", cmd);
-                tx.send(()).unwrap();
-            }
+            None => None,
+        };
+
+        let future = (entry.future_fn)(params);
+        match timeout {
+            Some(timeout) => Some((name, Box::pin(async move {
+                if with_timeout(timeout, future).await.is_err() {
+                    println!("{}: timed out after {}", name, timeout);
+                }
+            }))),
+            None => Some((name, future)),
         }
     }
 
@@ -1,10 +1,10 @@
-use alloc::{string::String, sync::Arc, vec::Vec};
-use futures_util::StreamExt;
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, string::String, sync::Arc, vec::Vec};
+use core::{future::Future, pin::Pin};
+use futures_util::{future::{select, Either}, StreamExt};
 use spin::Mutex;
 use lazy_static::lazy_static;
 use crate::{driver::usart::UsartCodeStream, print, task::executor::Executor};
 use gshell::CmdEntry;
-use futures_channel::oneshot;
 
 mod cmds;
 mod gshell;
@@ -17,6 +17,7 @@ const BS: u8 = 0x8; // Backspace
 const DEL: u8 = 0x7f;
 const TAB: u8 = 0x9;
 const ESC: u8 = 0x1b;
+const ETX: u8 = 0x3; // Ctrl-C
 
 lazy_static! {
     static ref GSHELL: Mutex<gshell::GShell> = Mutex::new(gshell::GShell::new());
@@ -26,6 +27,15 @@ fn register_cmd(name: &'static str, cmd: CmdEntry) {
     GSHELL.lock().add_cmd(name, cmd);
 }
 
+/// The future running `cmd` as if typed at the prompt, for commands that run others.
+fn cmd_future(cmd: &str, params: VecDeque<String>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+    GSHELL.lock().cmd_future(cmd, params).map(|(_, future)| future)
+}
+
+fn executor() -> Arc<Executor> {
+    GSHELL.lock().exec().expect("Executor not set")
+}
+
 pub(crate) async fn gshell(executor: Arc<Executor>) {
     GSHELL.lock().set_exec(executor);
     cmds::add_cmds();
@@ -101,9 +111,21 @@ pub(crate) async fn gshell(executor: Arc<Executor>) {
             }
         } else if code == b'\r' {
             if !line.is_empty() {
-                let (tx, rx) = oneshot::channel();
-                GSHELL.lock().command(&line, tx);
-                rx.await.unwrap();
+                let handle = GSHELL.lock().command(&line);
+                if let Some(mut handle) = handle {
+                    loop {
+                        match select(&mut handle, usart_code_stream.next()).await {
+                            Either::Left((res, _)) => {
+                                if res.is_err() {
+                                    print!("^C");
+                                }
+                                break;
+                            }
+                            Either::Right((Some(ETX), _)) => handle.abort(),
+                            Either::Right(_) => {}
+                        }
+                    }
+                }
                 if &line != history_vec.last().unwrap_or(&String::new()) {
                     history_vec.push(line.clone());
                 }
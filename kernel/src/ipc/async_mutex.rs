@@ -1,20 +1,44 @@
-use core::{cell::{RefCell, UnsafeCell}, future::poll_fn, sync::atomic::{AtomicBool, Ordering}, task::Poll};
+use core::{cell::UnsafeCell, future::poll_fn, pin::pin};
 
-use alloc::{sync::Arc, vec::Vec};
-use futures_util::task::AtomicWaker;
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+use super::wait_queue::{WaitList, WaitQueue, WaitState, Waiter};
+
+/// The mutex is held by someone else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TryLockErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LockTimeoutErr;
 
 struct State {
-    locked: AtomicBool,
-    waiter_list: Vec<Arc<AtomicWaker>>,
+    locked: bool,
+}
+
+impl State {
+    /// Hands the lock to the next waiter, or frees it if there is none.
+    fn unlock(queue: &mut WaitList<Self>) {
+        if !queue.grant_front() {
+            queue.state.locked = false;
+        }
+    }
+}
+
+impl WaitState for State {
+    fn cancel(queue: &mut WaitList<Self>, _weight: usize, granted: bool) {
+        if granted {
+            Self::unlock(queue);
+        }
+    }
 }
 
+/// A mutex for tasks, handed to waiters strictly in the order they asked for it.
 pub(crate) struct AsyncMutex<T: ?Sized> {
-    state: RefCell<State>,
+    queue: WaitQueue<State>,
     inner: UnsafeCell<T>,
 }
 
-unsafe impl<T: ?Sized> Sync for AsyncMutex<T> {}
-unsafe impl<T: ?Sized> Send for AsyncMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for AsyncMutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for AsyncMutex<T> {}
 
 impl<T> From<T> for AsyncMutex<T> {
     fn from(inner: T) -> Self {
@@ -31,44 +55,49 @@ impl<T: Default> Default for AsyncMutex<T> {
 impl<T> AsyncMutex<T> {
     pub(crate) const fn new(inner: T) -> Self {
         Self {
-            state: RefCell::new(State {
-                locked: AtomicBool::new(false),
-                waiter_list: Vec::new(),
-            }),
+            queue: WaitQueue::new(State { locked: false }),
             inner: UnsafeCell::new(inner),
         }
     }
 }
 
 impl<T: ?Sized> AsyncMutex<T> {
+    /// Waits for the lock. Dropping the future gives up its place in the queue.
     pub(crate) async  fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        let mut waiter = pin!(Waiter::new(&self.queue, 1));
         poll_fn(|cx| {
-            let mut state = self.state.borrow_mut();
-            if state.locked.load(Ordering::Acquire) {
-                let waker = Arc::new(AtomicWaker::new());
-                waker.register(&cx.waker());
-                state.waiter_list.push(waker);
-                Poll::Pending
-            } else {
-                state.locked.store(true, Ordering::Release);
-                Poll::Ready(AsyncMutexGuard {
-                    mutex: self,
-                })
-            }
-        }).await
+            waiter.as_mut().poll_with(cx, |queue, _| {
+                let free = !queue.state.locked && queue.is_empty();
+                queue.state.locked |= free;
+                free
+            })
+        }).await;
+        AsyncMutexGuard {
+            mutex: self,
+        }
+    }
+
+    /// Like `lock`, but gives up after `timeout`.
+    pub(crate) async fn lock_timeout<C: Clock>(&self, timeout: DurationOf<C>) -> Result<AsyncMutexGuard<'_, T>, LockTimeoutErr> {
+        with_timeout(timeout, self.lock()).await.map_err(|TimeoutError| LockTimeoutErr)
     }
 
-    pub(crate) fn try_lock(&self) -> Result<AsyncMutexGuard<'_, T>, ()> {
-        let state = self.state.borrow();
-        if state.locked.load(Ordering::Acquire) {
-            Err(())
+    /// Takes the lock if it is free and nobody is waiting for it.
+    pub(crate) fn try_lock(&self) -> Result<AsyncMutexGuard<'_, T>, TryLockErr> {
+        let mut queue = self.queue.lock();
+        if queue.state.locked || !queue.is_empty() {
+            Err(TryLockErr)
         } else {
-            state.locked.store(true, Ordering::Release);
+            queue.state.locked = true;
             Ok(AsyncMutexGuard {
                 mutex: self,
             })
         }
     }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.queue.lock().state.locked
+    }
 }
 
 pub(crate) struct AsyncMutexGuard<'a, T: ?Sized> {
@@ -77,11 +106,7 @@ pub(crate) struct AsyncMutexGuard<'a, T: ?Sized> {
 
 impl<'a, T: ?Sized> Drop for AsyncMutexGuard<'a, T> {
     fn drop(&mut self) {
-        let mut state = self.mutex.state.try_borrow_mut().unwrap();
-        state.locked.store(false, Ordering::Release);
-        if let Some(waker) = state.waiter_list.pop() {
-            waker.wake();
-        }
+        State::unlock(&mut self.mutex.queue.lock());
     }
 }
 
@@ -97,4 +122,4 @@ impl<'a, T: ?Sized> core::ops::DerefMut for AsyncMutexGuard<'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { &mut *self.mutex.inner.get() }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,11 @@
-use core::{cell::Cell, future::{poll_fn, Future}, task::Poll};
+use core::{cell::Cell, future::Future, pin::Pin, task::{Context, Poll, Waker}};
 
-use alloc::vec::Vec;
-use futures_util::task::AtomicWaker;
+use alloc::collections::btree_map::BTreeMap;
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WaitTimeoutErr;
 
 struct CellWraper<T> {
     cell: Cell<T>,
@@ -18,17 +22,22 @@ unsafe impl<T> Sync for CellWraper<T> {}
 
 enum State<T> {
     Non,
-    Waiting(Vec<AtomicWaker>),
+    /// Wakers of the pending `wait`s, keyed by their ids.
+    Waiting(BTreeMap<u64, Waker>),
     Signaled(T),
 }
 
 pub(crate) struct AsyncSignal<T> {
     state: CellWraper<State<T>>,
+    next_id: CellWraper<u64>,
 }
 
 impl<T> AsyncSignal<T> {
     pub(crate) const fn new() -> Self {
-        Self { state: CellWraper { cell: Cell::new(State::Non) } }
+        Self {
+            state: CellWraper { cell: Cell::new(State::Non) },
+            next_id: CellWraper { cell: Cell::new(0) },
+        }
     }
 
     fn set(&self, val: State<T>) {
@@ -47,8 +56,8 @@ impl<T: Clone> AsyncSignal<T> {
     pub(crate) fn signal(&self, val: T) {
         let old_state = self.state.cell.replace(State::Signaled(val));
         if let State::Waiting(waiters) = old_state {
-            for waiter in waiters {
-                waiter.wake();
+            for (_, waker) in waiters {
+                waker.wake();
             }
         }
     }
@@ -64,34 +73,73 @@ impl<T: Clone> AsyncSignal<T> {
         return res;
     }
 
+    /// The signalled value, without waiting.
+    pub(crate) fn try_wait(&self) -> Option<T> {
+        let state = self.state.replace(State::Non);
+        let res = if let State::Signaled(val) = &state {
+            Some(val.clone())
+        } else {
+            None
+        };
+        self.set(state);
+        res
+    }
+
+    /// Like `wait`, but gives up after `timeout`.
+    pub(crate) async fn wait_timeout<C: Clock>(&self, timeout: DurationOf<C>) -> Result<T, WaitTimeoutErr> {
+        with_timeout(timeout, self.wait()).await.map_err(|TimeoutError| WaitTimeoutErr)
+    }
+
     pub(crate) fn reset(&self) {
         self.set(State::Non);
     }
 
-    pub(crate) fn wait(&self) -> impl Future<Output = T> + Send + Sync + '_ {
-        poll_fn(|cx| {
-            let old_state = self.state.replace(State::Non);
-            match old_state {
-                State::Signaled(val) => {
-                    self.set(State::Signaled(val.clone()));
-                    Poll::Ready(val)
-                },
-                State::Non => {
-                    let waker = AtomicWaker::new();
-                    waker.register(&cx.waker());
-                    let mut vec = Vec::new();
-                    vec.push(waker);
-                    self.set(State::Waiting(vec));
-                    Poll::Pending
-                },
-                State::Waiting(mut vec) => {
-                    let waker = AtomicWaker::new();
-                    waker.register(&cx.waker());
-                    vec.push(waker);
-                    self.set(State::Waiting(vec));
-                    Poll::Pending
-                }
+    /// Waits until the signal is set. Dropping the future withdraws its waker.
+    pub(crate) fn wait(&self) -> Wait<'_, T> {
+        Wait { signal: self, id: None }
+    }
+}
+
+/// Future of `AsyncSignal::wait`.
+pub(crate) struct Wait<'a, T> {
+    signal: &'a AsyncSignal<T>,
+    id: Option<u64>,
+}
+
+impl<T: Clone> Future for Wait<'_, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let signal = self.signal;
+        let mut waiters = match signal.state.replace(State::Non) {
+            State::Signaled(val) => {
+                signal.set(State::Signaled(val.clone()));
+                self.id = None;
+                return Poll::Ready(val);
+            }
+            State::Non => BTreeMap::new(),
+            State::Waiting(waiters) => waiters,
+        };
+        let id = *self.id.get_or_insert_with(|| signal.next_id.replace(signal.next_id.cell.get() + 1));
+        waiters.insert(id, cx.waker().clone());
+        signal.set(State::Waiting(waiters));
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Wait<'_, T> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+        // after a signal or reset our waker is already gone
+        let state = match self.signal.state.replace(State::Non) {
+            State::Waiting(mut waiters) => {
+                waiters.remove(&id);
+                if waiters.is_empty() { State::Non } else { State::Waiting(waiters) }
             }
-        })
+            state => state,
+        };
+        self.signal.set(state);
     }
-}
\ No newline at end of file
+}
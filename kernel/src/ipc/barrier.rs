@@ -0,0 +1,68 @@
+use core::{future::poll_fn, pin::pin};
+
+use super::wait_queue::{WaitList, WaitQueue, WaitState, Waiter};
+
+struct State {
+    parties: usize,
+    arrived: usize,
+}
+
+impl WaitState for State {
+    fn cancel(queue: &mut WaitList<Self>, _weight: usize, granted: bool) {
+        // a waiter that leaves before the barrier opens no longer counts
+        if !granted {
+            queue.state.arrived -= 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// True for exactly one task per opening, the last to arrive.
+    pub(crate) fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
+
+/// Lets `parties` tasks wait for each other; it opens when the last one
+/// arrives and is then ready for the next round.
+pub(crate) struct Barrier {
+    queue: WaitQueue<State>,
+}
+
+impl Barrier {
+    pub(crate) const fn new(parties: usize) -> Self {
+        let parties = if parties == 0 { 1 } else { parties };
+        Self {
+            queue: WaitQueue::new(State { parties, arrived: 0 }),
+        }
+    }
+
+    pub(crate) fn parties(&self) -> usize {
+        self.queue.lock().state.parties
+    }
+
+    /// Waits until `parties` tasks are waiting. Dropping the future before
+    /// then takes its arrival back.
+    pub(crate) async fn wait(&self) -> BarrierWaitResult {
+        let mut is_leader = false;
+        let mut waiter = pin!(Waiter::new(&self.queue, 1));
+        poll_fn(|cx| {
+            waiter.as_mut().poll_with(cx, |queue, _| {
+                queue.state.arrived += 1;
+                if queue.state.arrived < queue.state.parties {
+                    return false;
+                }
+                queue.state.arrived = 0;
+                queue.grant_all();
+                is_leader = true;
+                true
+            })
+        }).await;
+        BarrierWaitResult { is_leader }
+    }
+}
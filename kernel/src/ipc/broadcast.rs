@@ -0,0 +1,226 @@
+//! Multi-consumer broadcast channel: every receiver sees every message.
+//!
+//! The channel keeps the last `N` messages. Sending never waits; a receiver
+//! that falls more than `N` messages behind loses the oldest ones and is told
+//! how many it missed with `RecvErr::Lagged`, then carries on from the oldest
+//! message still kept.
+
+use core::{future::poll_fn, task::{Context, Poll, Waker}};
+
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, sync::Arc};
+use spin::Mutex;
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SendErr<T>(pub(crate) T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecvErr {
+    /// The receiver fell behind and this many messages were dropped before it read them.
+    Lagged(u64),
+    /// Every sender is gone and all messages were read.
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TryRecvErr {
+    Empty,
+    Lagged(u64),
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecvTimeoutErr {
+    Timeout,
+    Lagged(u64),
+    Closed,
+}
+
+struct State<T, const N: usize> {
+    queue: VecDeque<T>,
+    /// Sequence number of the front of `queue`.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+    next_receiver: usize,
+    waiters: BTreeMap<usize, Waker>,
+}
+
+impl<T: Clone, const N: usize> State<T, N> {
+    fn tail(&self) -> u64 {
+        self.head + self.queue.len() as u64
+    }
+
+    fn send(&mut self, msg: T) -> Result<usize, SendErr<T>> {
+        if self.receivers == 0 {
+            return Err(SendErr(msg));
+        }
+        if self.queue.len() == N {
+            self.queue.pop_front();
+            self.head += 1;
+        }
+        self.queue.push_back(msg);
+        for (_, waker) in core::mem::take(&mut self.waiters) {
+            waker.wake();
+        }
+        Ok(self.receivers)
+    }
+
+    fn try_recv(&self, next: &mut u64) -> Result<T, TryRecvErr> {
+        if *next < self.head {
+            let lagged = self.head - *next;
+            *next = self.head;
+            return Err(TryRecvErr::Lagged(lagged));
+        }
+        match self.queue.get((*next - self.head) as usize) {
+            Some(msg) => {
+                *next += 1;
+                Ok(msg.clone())
+            }
+            None if self.senders == 0 => Err(TryRecvErr::Closed),
+            None => Err(TryRecvErr::Empty),
+        }
+    }
+}
+
+pub(crate) struct Sender<T, const N: usize> {
+    state: Arc<Mutex<State<T, N>>>,
+}
+
+impl<T: Clone, const N: usize> Sender<T, N> {
+    pub(crate) fn cap(&self) -> usize {
+        N
+    }
+
+    /// Sends to every current receiver, returning how many there are.
+    ///
+    /// Fails, handing the message back, if there are no receivers.
+    pub(crate) fn send(&self, msg: T) -> Result<usize, SendErr<T>> {
+        self.state.lock().send(msg)
+    }
+
+    /// A new receiver, seeing messages sent from now on.
+    pub(crate) fn subscribe(&self) -> Receiver<T, N> {
+        let mut state = self.state.lock();
+        let next = state.tail();
+        Receiver::register(&self.state, &mut state, next)
+    }
+
+    pub(crate) fn receiver_count(&self) -> usize {
+        self.state.lock().receivers
+    }
+}
+
+impl<T, const N: usize> Clone for Sender<T, N> {
+    fn clone(&self) -> Self {
+        self.state.lock().senders += 1;
+        Self { state: self.state.clone() }
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<T, N> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // wake the receivers so they see the channel closed
+            for (_, waker) in core::mem::take(&mut state.waiters) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+pub(crate) struct Receiver<T, const N: usize> {
+    state: Arc<Mutex<State<T, N>>>,
+    id: usize,
+    /// Sequence number of the next message to read.
+    next: u64,
+}
+
+impl<T: Clone, const N: usize> Receiver<T, N> {
+    fn register(shared: &Arc<Mutex<State<T, N>>>, state: &mut State<T, N>, next: u64) -> Self {
+        let id = state.next_receiver;
+        state.next_receiver += 1;
+        state.receivers += 1;
+        Self { state: shared.clone(), id, next }
+    }
+
+    pub(crate) fn cap(&self) -> usize {
+        N
+    }
+
+    /// Messages sent but not yet read by this receiver, up to `N`.
+    pub(crate) fn len(&self) -> usize {
+        let state = self.state.lock();
+        (state.tail() - self.next.max(state.head)) as usize
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvErr> {
+        self.state.lock().try_recv(&mut self.next)
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvErr>> {
+        let mut state = self.state.lock();
+        match state.try_recv(&mut self.next) {
+            Ok(msg) => Poll::Ready(Ok(msg)),
+            Err(TryRecvErr::Lagged(lagged)) => Poll::Ready(Err(RecvErr::Lagged(lagged))),
+            Err(TryRecvErr::Closed) => Poll::Ready(Err(RecvErr::Closed)),
+            Err(TryRecvErr::Empty) => {
+                state.waiters.insert(self.id, cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Waits for the next message. After `RecvErr::Lagged` the next call
+    /// returns the oldest message still kept.
+    pub(crate) async fn recv(&mut self) -> Result<T, RecvErr> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Like `recv`, but gives up after `timeout`.
+    pub(crate) async fn recv_timeout<C: Clock>(&mut self, timeout: DurationOf<C>) -> Result<T, RecvTimeoutErr> {
+        match with_timeout(timeout, self.recv()).await {
+            Ok(Ok(msg)) => Ok(msg),
+            Ok(Err(RecvErr::Lagged(lagged))) => Err(RecvTimeoutErr::Lagged(lagged)),
+            Ok(Err(RecvErr::Closed)) => Err(RecvTimeoutErr::Closed),
+            Err(TimeoutError) => Err(RecvTimeoutErr::Timeout),
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for Receiver<T, N> {
+    /// A receiver at the same position in the channel as this one.
+    fn clone(&self) -> Self {
+        let mut state = self.state.lock();
+        Self::register(&self.state, &mut state, self.next)
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<T, N> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.receivers -= 1;
+        state.waiters.remove(&self.id);
+    }
+}
+
+pub(crate) fn broadcast<T: Clone, const N: usize>() -> (Sender<T, N>, Receiver<T, N>) {
+    assert!(N > 0, "broadcast capacity must be at least 1");
+    let state = Arc::new(Mutex::new(State {
+        queue: VecDeque::with_capacity(N),
+        head: 0,
+        senders: 1,
+        receivers: 0,
+        next_receiver: 0,
+        waiters: BTreeMap::new(),
+    }));
+    let receiver = Receiver::register(&state, &mut state.lock(), 0);
+    (Sender { state }, receiver)
+}
@@ -0,0 +1,339 @@
+//! Kernel-wide publish/subscribe bus of named, typed topics.
+//!
+//! Tasks look topics up by name at runtime instead of passing channel ends
+//! around. A topic is created by its first publisher or subscriber and
+//! carries one message type; every subscriber has its own bounded queue and
+//! chooses what happens when it is full: drop its oldest message, or hold the
+//! publishers back until it catches up.
+//!
+//! ```ignore
+//! let imu = bus::publisher::<ImuSample>("imu")?;
+//! let mut fused = bus::subscribe::<ImuSample>("imu", 8, Overflow::DropOldest)?;
+//! imu.publish(sample).await;
+//! let sample = fused.recv().await;
+//! ```
+
+use core::{any::{type_name, Any}, fmt, future::poll_fn, pin::Pin, task::{Context, Poll, Waker}};
+
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, string::{String, ToString}, sync::Arc, vec::Vec};
+use futures_core::Stream;
+use spin::Mutex;
+
+static TOPICS: Mutex<BTreeMap<String, Arc<dyn AnyTopic>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BusErr {
+    /// The topic already exists with another message type, named here.
+    TypeMismatch(&'static str),
+}
+
+impl fmt::Display for BusErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusErr::TypeMismatch(existing) => write!(f, "topic carries {}", existing),
+        }
+    }
+}
+
+/// A backpressure subscriber is full; the message is handed back.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct TryPublishErr<T>(pub(crate) T);
+
+/// What a subscriber's full queue does with a new message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Overflow {
+    /// Drop the oldest queued message to make room; publishers never wait.
+    DropOldest,
+    /// Make publishers wait until this subscriber has room.
+    Backpressure,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Overflow::DropOldest => "drop-oldest",
+            Overflow::Backpressure => "backpressure",
+        })
+    }
+}
+
+pub(crate) struct TopicInfo {
+    pub(crate) name: String,
+    pub(crate) type_name: &'static str,
+    pub(crate) publishers: usize,
+    pub(crate) subscribers: usize,
+    /// Messages published since the topic was created.
+    pub(crate) published: u64,
+    /// Messages subscribers lost to `Overflow::DropOldest`.
+    pub(crate) dropped: u64,
+}
+
+struct SubQueue<T> {
+    queue: VecDeque<T>,
+    cap: usize,
+    overflow: Overflow,
+    dropped: u64,
+    waker: Option<Waker>,
+}
+
+impl<T> SubQueue<T> {
+    fn blocks(&self) -> bool {
+        self.overflow == Overflow::Backpressure && self.queue.len() == self.cap
+    }
+}
+
+struct TopicState<T> {
+    subscribers: BTreeMap<u64, SubQueue<T>>,
+    next_subscriber: u64,
+    publishers: usize,
+    published: u64,
+    dropped: u64,
+    /// Publishers waiting for a backpressure subscriber to make room, keyed by `PublishWaiter::id`.
+    blocked: BTreeMap<u64, Waker>,
+    next_blocked: u64,
+}
+
+impl<T: Clone> TopicState<T> {
+    fn try_publish(&mut self, msg: T) -> Result<usize, TryPublishErr<T>> {
+        if self.subscribers.values().any(SubQueue::blocks) {
+            return Err(TryPublishErr(msg));
+        }
+        for sub in self.subscribers.values_mut() {
+            if sub.queue.len() == sub.cap {
+                sub.queue.pop_front();
+                sub.dropped += 1;
+                self.dropped += 1;
+            }
+            sub.queue.push_back(msg.clone());
+            if let Some(waker) = sub.waker.take() {
+                waker.wake();
+            }
+        }
+        self.published += 1;
+        Ok(self.subscribers.len())
+    }
+
+    fn wake_publishers(&mut self) {
+        for (_, waker) in core::mem::take(&mut self.blocked) {
+            waker.wake();
+        }
+    }
+}
+
+/// A blocked `publish`'s registration, withdrawn when it finishes or is dropped.
+struct PublishWaiter<'a, T> {
+    topic: &'a Topic<T>,
+    id: Option<u64>,
+}
+
+impl<T> Drop for PublishWaiter<'_, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.topic.state.lock().blocked.remove(&id);
+        }
+    }
+}
+
+struct Topic<T> {
+    state: Mutex<TopicState<T>>,
+}
+
+/// A topic with its message type erased, as the registry holds it.
+trait AnyTopic: Send + Sync {
+    fn type_name(&self) -> &'static str;
+    fn info(&self, name: &str) -> TopicInfo;
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Clone + Send + 'static> AnyTopic for Topic<T> {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn info(&self, name: &str) -> TopicInfo {
+        let state = self.state.lock();
+        TopicInfo {
+            name: name.to_string(),
+            type_name: type_name::<T>(),
+            publishers: state.publishers,
+            subscribers: state.subscribers.len(),
+            published: state.published,
+            dropped: state.dropped,
+        }
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// The topic called `name`, created for `T` if it does not exist yet.
+fn topic<T: Clone + Send + 'static>(name: &str) -> Result<Arc<Topic<T>>, BusErr> {
+    let mut topics = TOPICS.lock();
+    let topic = topics.entry(name.to_string()).or_insert_with(|| {
+        Arc::new(Topic::<T> {
+            state: Mutex::new(TopicState {
+                subscribers: BTreeMap::new(),
+                next_subscriber: 0,
+                publishers: 0,
+                published: 0,
+                dropped: 0,
+                blocked: BTreeMap::new(),
+                next_blocked: 0,
+            }),
+        })
+    });
+    let type_name = topic.type_name();
+    topic.clone().into_any().downcast::<Topic<T>>().map_err(|_| BusErr::TypeMismatch(type_name))
+}
+
+pub(crate) struct Publisher<T: Clone + Send + 'static> {
+    topic: Arc<Topic<T>>,
+}
+
+impl<T: Clone + Send + 'static> Publisher<T> {
+    /// Delivers `msg` to every current subscriber, waiting while a
+    /// backpressure subscriber is full. Returns how many got it.
+    pub(crate) async fn publish(&self, msg: T) -> usize {
+        let mut msg = Some(msg);
+        let mut waiter = PublishWaiter { topic: &self.topic, id: None };
+        poll_fn(|cx| {
+            let mut state = self.topic.state.lock();
+            match state.try_publish(msg.take().expect("message already published")) {
+                Ok(delivered) => {
+                    if let Some(id) = waiter.id.take() {
+                        state.blocked.remove(&id);
+                    }
+                    Poll::Ready(delivered)
+                }
+                Err(TryPublishErr(back)) => {
+                    msg = Some(back);
+                    let id = *waiter.id.get_or_insert_with(|| {
+                        state.next_blocked += 1;
+                        state.next_blocked
+                    });
+                    // a re-poll replaces the waker registered last time
+                    state.blocked.insert(id, cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }).await
+    }
+
+    /// Like `publish`, but fails instead of waiting for a full backpressure subscriber.
+    pub(crate) fn try_publish(&self, msg: T) -> Result<usize, TryPublishErr<T>> {
+        self.topic.state.lock().try_publish(msg)
+    }
+
+    pub(crate) fn subscriber_count(&self) -> usize {
+        self.topic.state.lock().subscribers.len()
+    }
+}
+
+impl<T: Clone + Send + 'static> Clone for Publisher<T> {
+    fn clone(&self) -> Self {
+        self.topic.state.lock().publishers += 1;
+        Self { topic: self.topic.clone() }
+    }
+}
+
+impl<T: Clone + Send + 'static> Drop for Publisher<T> {
+    fn drop(&mut self) {
+        self.topic.state.lock().publishers -= 1;
+    }
+}
+
+/// A subscription; also a `Stream` of the topic's messages.
+pub(crate) struct Subscriber<T: Clone + Send + 'static> {
+    topic: Arc<Topic<T>>,
+    id: u64,
+}
+
+impl<T: Clone + Send + 'static> Subscriber<T> {
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.topic.state.lock();
+        let sub = state.subscribers.get_mut(&self.id).expect("bus subscriber vanished");
+        match sub.queue.pop_front() {
+            Some(msg) => {
+                if sub.overflow == Overflow::Backpressure {
+                    state.wake_publishers();
+                }
+                Poll::Ready(msg)
+            }
+            None => {
+                sub.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    pub(crate) async fn recv(&mut self) -> T {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<T> {
+        let mut state = self.topic.state.lock();
+        let msg = state.subscribers.get_mut(&self.id)?.queue.pop_front();
+        if msg.is_some() {
+            state.wake_publishers();
+        }
+        msg
+    }
+
+    /// Messages queued for this subscriber.
+    pub(crate) fn len(&self) -> usize {
+        self.topic.state.lock().subscribers.get(&self.id).map_or(0, |sub| sub.queue.len())
+    }
+
+    /// Messages this subscriber lost to `Overflow::DropOldest`.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.topic.state.lock().subscribers.get(&self.id).map_or(0, |sub| sub.dropped)
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream for Subscriber<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx).map(Some)
+    }
+}
+
+impl<T: Clone + Send + 'static> Drop for Subscriber<T> {
+    fn drop(&mut self) {
+        let mut state = self.topic.state.lock();
+        state.subscribers.remove(&self.id);
+        // a full backpressure subscriber leaving unblocks the publishers
+        state.wake_publishers();
+    }
+}
+
+/// A publisher on topic `name`, creating the topic if needed.
+pub(crate) fn publisher<T: Clone + Send + 'static>(name: &str) -> Result<Publisher<T>, BusErr> {
+    let topic = topic::<T>(name)?;
+    topic.state.lock().publishers += 1;
+    Ok(Publisher { topic })
+}
+
+/// Subscribes to topic `name` with a queue of `cap` messages, creating the
+/// topic if needed. Only messages published from now on are received.
+pub(crate) fn subscribe<T: Clone + Send + 'static>(name: &str, cap: usize, overflow: Overflow) -> Result<Subscriber<T>, BusErr> {
+    assert!(cap > 0, "bus subscriber queue must hold at least one message");
+    let topic = topic::<T>(name)?;
+    let mut state = topic.state.lock();
+    let id = state.next_subscriber;
+    state.next_subscriber += 1;
+    state.subscribers.insert(id, SubQueue {
+        queue: VecDeque::with_capacity(cap),
+        cap,
+        overflow,
+        dropped: 0,
+        waker: None,
+    });
+    drop(state);
+    Ok(Subscriber { topic, id })
+}
+
+pub(crate) fn topics() -> Vec<TopicInfo> {
+    TOPICS.lock().iter().map(|(name, topic)| topic.info(name)).collect()
+}
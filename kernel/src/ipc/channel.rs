@@ -1,22 +1,54 @@
-use core::{future::poll_fn, task::{Context, Poll}};
+use core::{future::poll_fn, pin::Pin, sync::atomic::{AtomicBool, AtomicUsize, Ordering}, task::{Context, Poll, Waker}};
 
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{collections::{btree_map::BTreeMap, vec_deque::VecDeque}, sync::Arc};
+use futures_core::{FusedStream, Stream};
 use futures_util::task::AtomicWaker;
 
-use super::async_mutex::AsyncMutex;
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+use super::async_mutex::{AsyncMutex, AsyncMutexGuard};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum TryRecvErr {
     Empty,
+    /// Every sender is gone or the channel was closed, and all messages were read.
+    Closed,
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub(crate) enum TrySendErr<T> {
     Full(T),
+    /// The receiver is gone or the channel was closed.
+    Closed(T),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecvErr {
+    Closed,
+}
+
+/// The receiver is gone or the channel was closed; the message is handed back.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SendErr<T>(pub(crate) T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecvTimeoutErr {
+    Timeout,
+    Closed,
+}
+
+/// Either way the message is handed back.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SendTimeoutErr<T> {
+    Timeout(T),
+    Closed(T),
 }
 
 struct ChannelState<T, const N: usize> {
     queue: VecDeque<T>,
     recv_waker: AtomicWaker,
-    send_waker: AtomicWaker,
+    /// One entry per send waiting for room, keyed by `SendWaiter::id`.
+    send_wakers: BTreeMap<u64, Waker>,
+    next_send_id: u64,
 }
 
 impl<T, const N: usize> ChannelState<T, N> {
@@ -24,7 +56,8 @@ impl<T, const N: usize> ChannelState<T, N> {
         Self {
             queue: VecDeque::new(),
             recv_waker: AtomicWaker::new(),
-            send_waker: AtomicWaker::new(),
+            send_wakers: BTreeMap::new(),
+            next_send_id: 0,
         }
     }
 
@@ -38,28 +71,45 @@ impl<T, const N: usize> ChannelState<T, N> {
 
     fn clear(&mut self) {
         self.queue.clear();
+        self.wake_senders();
     }
 
     fn len(&self) -> usize {
         self.queue.len()
     }
 
-    fn rcve_with_context(&mut self, cx: &mut Context<'_>) -> Result<T, TryRecvErr> {
+    fn wake_senders(&mut self) {
+        for (_, waker) in core::mem::take(&mut self.send_wakers) {
+            waker.wake();
+        }
+    }
+
+    fn try_rcve(&mut self, closed: bool) -> Result<T, TryRecvErr> {
         if self.is_full() {
-            self.send_waker.wake();
+            self.wake_senders();
         }
 
         if let Some(msg) = self.queue.pop_front() {
             Ok(msg)
+        } else if closed {
+            Err(TryRecvErr::Closed)
         } else {
-            self.recv_waker.register(cx.waker());
             Err(TryRecvErr::Empty)
         }
     }
 
-    fn send_with_context(&mut self, msg: T, cx: &mut Context<'_>) -> Result<(), TrySendErr<T>> {
-        if self.is_full() {
-            self.send_waker.register(cx.waker());
+    fn rcve_with_context(&mut self, closed: bool, cx: &mut Context<'_>) -> Result<T, TryRecvErr> {
+        let res = self.try_rcve(closed);
+        if let Err(TryRecvErr::Empty) = res {
+            self.recv_waker.register(cx.waker());
+        }
+        res
+    }
+
+    fn try_send(&mut self, msg: T, closed: bool) -> Result<(), TrySendErr<T>> {
+        if closed {
+            Err(TrySendErr::Closed(msg))
+        } else if self.is_full() {
             Err(TrySendErr::Full(msg))
         } else {
             self.queue.push_back(msg);
@@ -67,16 +117,58 @@ impl<T, const N: usize> ChannelState<T, N> {
             Ok(())
         }
     }
+
+    fn send_with_context(&mut self, msg: T, closed: bool, id: &mut Option<u64>, cx: &mut Context<'_>) -> Result<(), TrySendErr<T>> {
+        let res = self.try_send(msg, closed);
+        if let Err(TrySendErr::Full(_)) = res {
+            let id = *id.get_or_insert_with(|| {
+                self.next_send_id += 1;
+                self.next_send_id
+            });
+            // a re-poll replaces the waker registered last time
+            self.send_wakers.insert(id, cx.waker().clone());
+        } else if let Some(id) = id.take() {
+            self.send_wakers.remove(&id);
+        }
+        res
+    }
+}
+
+/// A send's registration for room in the channel, withdrawn when the send
+/// finishes or is dropped, e.g. by a timeout.
+struct SendWaiter<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+    id: Option<u64>,
+}
+
+impl<'a, T, const N: usize> SendWaiter<'a, T, N> {
+    fn new(channel: &'a Channel<T, N>) -> Self {
+        Self { channel, id: None }
+    }
+}
+
+impl<T, const N: usize> Drop for SendWaiter<'_, T, N> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.channel.spin_lock().send_wakers.remove(&id);
+        }
+    }
 }
 
 struct Channel<T, const N: usize> {
     inner: AsyncMutex<ChannelState<T, N>>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    closed: AtomicBool,
 }
 
 impl<T, const N: usize> Channel<T, N> {
     fn new() -> Self {
         Self {
             inner: AsyncMutex::new(ChannelState::new()),
+            senders: AtomicUsize::new(1),
+            receivers: AtomicUsize::new(1),
+            closed: AtomicBool::new(false),
         }
     }
 
@@ -104,46 +196,101 @@ impl<T, const N: usize> Channel<T, N> {
         self.inner.lock().await.clear();
     }
 
-    async fn send(&self, msg: T) {
-        let mut msg = Some(msg);
-
-        poll_fn(|cx| {
-            match msg.take() {
-                Some(m1) => {
-                    let mut guard = loop {
-                        if let Ok(guard) = self.inner.try_lock() {
-                            break guard;
-                        }
-                    };
-                    match guard.send_with_context(m1, cx) {
-                        Ok(..) => Poll::Ready(()),
-                        Err(TrySendErr::Full(m2)) => {
-                            msg = Some(m2);
-                            drop(guard);
-                            Poll::Pending
-                        }
-                    }
-                },
-                None => panic!("Message cannot be None"),
+    fn spin_lock(&self) -> AsyncMutexGuard<'_, ChannelState<T, N>> {
+        loop {
+            if let Ok(guard) = self.inner.try_lock() {
+                break guard;
             }
-        }).await
+        }
+    }
+
+    /// Closed for senders: the receiver is gone or someone closed the channel.
+    fn is_send_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire) || self.receivers.load(Ordering::Acquire) == 0
+    }
+
+    /// Closed for the receiver: no more messages can arrive.
+    fn is_recv_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire) || self.senders.load(Ordering::Acquire) == 0
     }
 
-    async fn recv(&self) -> T {
-        poll_fn(|cx| {
-            let mut guard = loop {
-                if let Ok(guard) = self.inner.try_lock() {
-                    break guard;
+    /// Marks the channel closed and wakes everyone waiting on it.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.wake_all();
+    }
+
+    fn wake_all(&self) {
+        let mut guard = self.spin_lock();
+        guard.wake_senders();
+        guard.recv_waker.wake();
+    }
+
+    fn poll_send(&self, msg: &mut Option<T>, waiter: &mut SendWaiter<'_, T, N>, cx: &mut Context<'_>) -> Poll<Result<(), SendErr<T>>> {
+        match msg.take() {
+            Some(m1) => {
+                let mut guard = self.spin_lock();
+                match guard.send_with_context(m1, self.is_send_closed(), &mut waiter.id, cx) {
+                    Ok(..) => Poll::Ready(Ok(())),
+                    Err(TrySendErr::Closed(m2)) => Poll::Ready(Err(SendErr(m2))),
+                    Err(TrySendErr::Full(m2)) => {
+                        *msg = Some(m2);
+                        drop(guard);
+                        Poll::Pending
+                    }
                 }
-            };
-            match guard.rcve_with_context(cx) {
-                Ok(msg) => Poll::Ready(msg),
-                Err(TryRecvErr::Empty) => {
-                    drop(guard);
-                    Poll::Pending
-                },
-            }
-        }).await
+            },
+            None => panic!("Message cannot be None"),
+        }
+    }
+
+    async fn send(&self, msg: T) -> Result<(), SendErr<T>> {
+        let mut msg = Some(msg);
+        let mut waiter = SendWaiter::new(self);
+        poll_fn(|cx| self.poll_send(&mut msg, &mut waiter, cx)).await
+    }
+
+    fn try_send(&self, msg: T) -> Result<(), TrySendErr<T>> {
+        let mut guard = self.spin_lock();
+        guard.try_send(msg, self.is_send_closed())
+    }
+
+    async fn send_timeout<C: Clock>(&self, msg: T, timeout: DurationOf<C>) -> Result<(), SendTimeoutErr<T>> {
+        let mut msg = Some(msg);
+        let mut waiter = SendWaiter::new(self);
+        match with_timeout(timeout, poll_fn(|cx| self.poll_send(&mut msg, &mut waiter, cx))).await {
+            Ok(res) => res.map_err(|SendErr(msg)| SendTimeoutErr::Closed(msg)),
+            // a pending send always puts the message back
+            Err(TimeoutError) => Err(SendTimeoutErr::Timeout(msg.take().unwrap())),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvErr>> {
+        let mut guard = self.spin_lock();
+        match guard.rcve_with_context(self.is_recv_closed(), cx) {
+            Ok(msg) => Poll::Ready(Ok(msg)),
+            Err(TryRecvErr::Closed) => Poll::Ready(Err(RecvErr::Closed)),
+            Err(TryRecvErr::Empty) => {
+                drop(guard);
+                Poll::Pending
+            },
+        }
+    }
+
+    async fn recv(&self) -> Result<T, RecvErr> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvErr> {
+        let mut guard = self.spin_lock();
+        guard.try_rcve(self.is_recv_closed())
+    }
+
+    async fn recv_timeout<C: Clock>(&self, timeout: DurationOf<C>) -> Result<T, RecvTimeoutErr> {
+        match with_timeout(timeout, self.recv()).await {
+            Ok(res) => res.map_err(|RecvErr::Closed| RecvTimeoutErr::Closed),
+            Err(TimeoutError) => Err(RecvTimeoutErr::Timeout),
+        }
     }
 }
 
@@ -177,12 +324,49 @@ impl<T, const N: usize> Sender<T, N> {
         self.channel.is_full().await
     }
 
-    pub(crate) async fn send(&self, msg: T) {
-        self.channel.send(msg).await;
+    /// Whether sending will fail: the receiver is gone or the channel was closed.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.channel.is_send_closed()
+    }
+
+    /// Closes the channel for every sender; the receiver still gets the queued messages.
+    pub(crate) fn close(&self) {
+        self.channel.close();
+    }
+
+    /// Waits for room and sends, or hands the message back once the channel is closed.
+    pub(crate) async fn send(&self, msg: T) -> Result<(), SendErr<T>> {
+        self.channel.send(msg).await
+    }
+
+    /// Sends only if there is room right now.
+    pub(crate) fn try_send(&self, msg: T) -> Result<(), TrySendErr<T>> {
+        self.channel.try_send(msg)
+    }
+
+    /// Like `send`, but gives up and hands the message back after `timeout`.
+    pub(crate) async fn send_timeout<C: Clock>(&self, msg: T, timeout: DurationOf<C>) -> Result<(), SendTimeoutErr<T>> {
+        self.channel.send_timeout(msg, timeout).await
+    }
+}
+
+impl<T, const N: usize> Clone for Sender<T, N> {
+    fn clone(&self) -> Self {
+        self.channel.senders.fetch_add(1, Ordering::AcqRel);
+        Self { channel: self.channel.clone() }
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<T, N> {
+    fn drop(&mut self) {
+        if self.channel.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.channel.wake_all();
+        }
     }
 }
 
 
+/// The receiving end; also a `Stream` of the messages that ends once the channel is closed.
 pub(crate) struct Receiver<T, const N: usize> {
     channel: Arc<Channel<T, N>>,
 }
@@ -212,9 +396,52 @@ impl<T, const N: usize> Receiver<T, N> {
         self.channel.is_full().await
     }
 
-    pub(crate) async fn recv(&self) -> T {
+    /// Whether no more messages can arrive; some may still be queued.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.channel.is_recv_closed()
+    }
+
+    /// Stops further sends; the messages already queued can still be received.
+    pub(crate) fn close(&self) {
+        self.channel.close();
+    }
+
+    /// Waits for the next message. Fails once the channel is closed (or every
+    /// sender is gone) and the queue is drained.
+    pub(crate) async fn recv(&self) -> Result<T, RecvErr> {
         self.channel.recv().await
     }
+
+    /// Receives only if a message is queued right now.
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvErr> {
+        self.channel.try_recv()
+    }
+
+    /// Like `recv`, but gives up after `timeout`.
+    pub(crate) async fn recv_timeout<C: Clock>(&self, timeout: DurationOf<C>) -> Result<T, RecvTimeoutErr> {
+        self.channel.recv_timeout(timeout).await
+    }
+}
+
+impl<T, const N: usize> Stream for Receiver<T, N> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.channel.poll_recv(cx).map(Result::ok)
+    }
+}
+
+impl<T, const N: usize> FusedStream for Receiver<T, N> {
+    fn is_terminated(&self) -> bool {
+        self.channel.is_recv_closed() && self.channel.spin_lock().is_empty()
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<T, N> {
+    fn drop(&mut self) {
+        self.channel.receivers.fetch_sub(1, Ordering::AcqRel);
+        self.channel.wake_all();
+    }
 }
 
 pub(crate) fn channel<T, const N: usize>() -> (Sender<T, N>, Receiver<T, N>) {
@@ -222,4 +449,4 @@ pub(crate) fn channel<T, const N: usize>() -> (Sender<T, N>, Receiver<T, N>) {
     let sender = Sender { channel: channel.clone() };
     let receiver = Receiver { channel };
     (sender, receiver)
-}
\ No newline at end of file
+}
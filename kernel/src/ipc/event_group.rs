@@ -0,0 +1,233 @@
+//! Event groups: a 32-bit flag set tasks can wait on, as in FreeRTOS.
+//!
+//! A task waits for any or all bits of a mask, optionally clearing them when
+//! its wait ends. Interrupts set bits through `event_group_set_bits_from_isr`
+//! with the group's handle; the bits change at once, and the waiters are
+//! checked by the executor on its next pass, since an ISR must not take the
+//! waiter lock. The prototype is in `board/inc/isr.h`:
+//!
+//! ```c
+//! extern int event_group_set_bits_from_isr(const event_group_t *group, uint32_t bits);
+//! ```
+
+use core::{future::Future, pin::Pin, ptr, sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering}, task::{Context, Poll, Waker}};
+
+use alloc::collections::btree_map::BTreeMap;
+use spin::Mutex;
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+
+/// Groups with bits set by an ISR and waiters not yet checked, linked through `isr_next`.
+static ISR_PENDING: AtomicPtr<EventGroup> = AtomicPtr::new(ptr::null_mut());
+
+/// The wait timed out; holds the bits at that moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WaitTimeoutErr(pub(crate) u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WaitFor {
+    /// Any bit of the mask is set.
+    Any,
+    /// Every bit of the mask is set.
+    All,
+}
+
+impl WaitFor {
+    fn is_met(self, bits: u32, mask: u32) -> bool {
+        match self {
+            WaitFor::Any => bits & mask != 0,
+            WaitFor::All => bits & mask == mask,
+        }
+    }
+}
+
+struct Waiting {
+    mask: u32,
+    wait_for: WaitFor,
+    clear_on_exit: bool,
+    waker: Option<Waker>,
+    /// The bits that ended the wait, once they have.
+    result: Option<u32>,
+}
+
+struct State {
+    waiters: BTreeMap<u64, Waiting>,
+    next_id: u64,
+}
+
+pub(crate) struct EventGroup {
+    bits: AtomicU32,
+    state: Mutex<State>,
+    isr_queued: AtomicBool,
+    isr_next: AtomicPtr<EventGroup>,
+}
+
+impl Default for EventGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventGroup {
+    pub(crate) const fn new() -> Self {
+        Self {
+            bits: AtomicU32::new(0),
+            state: Mutex::new(State { waiters: BTreeMap::new(), next_id: 0 }),
+            isr_queued: AtomicBool::new(false),
+            isr_next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// The handle C code passes to `event_group_set_bits_from_isr`.
+    pub(crate) fn as_handle(&'static self) -> *const EventGroup {
+        self
+    }
+
+    pub(crate) fn get(&self) -> u32 {
+        self.bits.load(Ordering::Acquire)
+    }
+
+    /// Sets `bits` and ends the waits they satisfy. Returns the bits
+    /// afterwards, which no longer include any cleared on exit.
+    pub(crate) fn set(&self, bits: u32) -> u32 {
+        self.bits.fetch_or(bits, Ordering::AcqRel);
+        self.dispatch();
+        self.get()
+    }
+
+    /// Clears `bits`, returning the bits before.
+    pub(crate) fn clear(&self, bits: u32) -> u32 {
+        self.bits.fetch_and(!bits, Ordering::AcqRel)
+    }
+
+    /// Waits until any or all bits of `mask` are set, returning the bits at
+    /// that moment. With `clear_on_exit` the mask's bits are then cleared.
+    pub(crate) fn wait(&self, mask: u32, wait_for: WaitFor, clear_on_exit: bool) -> WaitBits<'_> {
+        assert!(mask != 0, "event group wait needs a non-empty mask");
+        WaitBits { group: self, mask, wait_for, clear_on_exit, id: None }
+    }
+
+    /// Like `wait`, but gives up after `timeout`.
+    pub(crate) async fn wait_timeout<C: Clock>(&self, mask: u32, wait_for: WaitFor, clear_on_exit: bool, timeout: DurationOf<C>) -> Result<u32, WaitTimeoutErr> {
+        with_timeout(timeout, self.wait(mask, wait_for, clear_on_exit)).await.map_err(|TimeoutError| WaitTimeoutErr(self.get()))
+    }
+
+    /// Ends every wait the current bits satisfy. All waiters see the same
+    /// bits; those cleared on exit are cleared once every waiter was checked.
+    fn dispatch(&self) {
+        let mut state = self.state.lock();
+        let bits = self.get();
+        let mut clear = 0;
+        for waiting in state.waiters.values_mut().filter(|waiting| waiting.result.is_none()) {
+            if waiting.wait_for.is_met(bits, waiting.mask) {
+                waiting.result = Some(bits);
+                if waiting.clear_on_exit {
+                    clear |= waiting.mask;
+                }
+                if let Some(waker) = waiting.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        if clear != 0 {
+            self.clear(clear);
+        }
+    }
+}
+
+/// Future of `EventGroup::wait`; dropping it withdraws the wait.
+pub(crate) struct WaitBits<'a> {
+    group: &'a EventGroup,
+    mask: u32,
+    wait_for: WaitFor,
+    clear_on_exit: bool,
+    id: Option<u64>,
+}
+
+impl Future for WaitBits<'_> {
+    type Output = u32;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32> {
+        let group = self.group;
+        let mut state = group.state.lock();
+        match self.id {
+            Some(id) => {
+                let waiting = state.waiters.get_mut(&id).expect("event group waiter vanished");
+                if let Some(bits) = waiting.result {
+                    state.waiters.remove(&id);
+                    self.id = None;
+                    return Poll::Ready(bits);
+                }
+                waiting.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => {
+                // checked under the lock, so a set landing after it dispatches to us
+                let bits = group.get();
+                if self.wait_for.is_met(bits, self.mask) {
+                    if self.clear_on_exit {
+                        group.clear(self.mask);
+                    }
+                    return Poll::Ready(bits);
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.insert(id, Waiting {
+                    mask: self.mask,
+                    wait_for: self.wait_for,
+                    clear_on_exit: self.clear_on_exit,
+                    waker: Some(cx.waker().clone()),
+                    result: None,
+                });
+                self.id = Some(id);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for WaitBits<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.group.state.lock().waiters.remove(&id);
+        }
+    }
+}
+
+/// Sets `bits` of the group behind `group` (see `EventGroup::as_handle`).
+/// Safe to call from interrupts. Returns 0 on success, -1 for a null handle.
+#[no_mangle]
+pub extern "C" fn event_group_set_bits_from_isr(group: *const EventGroup, bits: u32) -> i32 {
+    // SAFETY: handles only come from `as_handle`, so point to a static group
+    let Some(group) = (unsafe { group.as_ref() }) else {
+        return -1;
+    };
+    group.bits.fetch_or(bits, Ordering::AcqRel);
+    if !group.isr_queued.swap(true, Ordering::AcqRel) {
+        let mut head = ISR_PENDING.load(Ordering::Acquire);
+        loop {
+            group.isr_next.store(head, Ordering::Relaxed);
+            match ISR_PENDING.compare_exchange_weak(head, group as *const _ as *mut _, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+    }
+    0
+}
+
+pub(crate) fn has_isr_pending() -> bool {
+    !ISR_PENDING.load(Ordering::Acquire).is_null()
+}
+
+/// Checks the waiters of every group an ISR set bits in; called by the executor.
+pub(crate) fn drain_isr_pending() {
+    let mut next = ISR_PENDING.swap(ptr::null_mut(), Ordering::AcqRel);
+    // SAFETY: only static groups are ever queued
+    while let Some(group) = unsafe { next.as_ref() } {
+        next = group.isr_next.load(Ordering::Acquire);
+        // read the link first: once unqueued, an ISR may queue the group again
+        group.isr_queued.store(false, Ordering::Release);
+        group.dispatch();
+    }
+}
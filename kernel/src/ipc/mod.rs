@@ -2,4 +2,13 @@
 
 pub(crate) mod async_mutex;
 pub(crate) mod async_signal;
-pub(crate) mod channel;
\ No newline at end of file
+pub(crate) mod barrier;
+pub(crate) mod broadcast;
+pub(crate) mod bus;
+pub(crate) mod channel;
+pub(crate) mod event_group;
+pub(crate) mod notify;
+pub(crate) mod rwlock;
+pub(crate) mod semaphore;
+pub(crate) mod wait_queue;
+pub(crate) mod watch;
\ No newline at end of file
@@ -0,0 +1,73 @@
+use core::{future::poll_fn, pin::pin};
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+use super::wait_queue::{WaitList, WaitQueue, WaitState, Waiter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NotifiedTimeoutErr;
+
+struct State {
+    /// A `notify_one` nobody was waiting for, kept for the next `notified`.
+    permit: bool,
+}
+
+impl State {
+    fn notify_one(queue: &mut WaitList<Self>) {
+        if !queue.grant_front() {
+            queue.state.permit = true;
+        }
+    }
+}
+
+impl WaitState for State {
+    fn cancel(queue: &mut WaitList<Self>, _weight: usize, granted: bool) {
+        // pass a `notify_one` the dropped waiter never saw on to the next one;
+        // `notify_all` releases waiters without granting, so leaves no permit
+        if granted {
+            Self::notify_one(queue);
+        }
+    }
+}
+
+/// Wakes waiting tasks without carrying a value.
+///
+/// `notify_one` wakes the longest waiting task, or is kept for the next one
+/// to wait if none is; `notify_all` wakes every task waiting right now.
+pub(crate) struct Notify {
+    queue: WaitQueue<State>,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notify {
+    pub(crate) const fn new() -> Self {
+        Self {
+            queue: WaitQueue::new(State { permit: false }),
+        }
+    }
+
+    pub(crate) fn notify_one(&self) {
+        State::notify_one(&mut self.queue.lock());
+    }
+
+    pub(crate) fn notify_all(&self) {
+        self.queue.lock().grant_all();
+    }
+
+    /// Waits for a notification. Dropping the future gives up its place in the queue.
+    pub(crate) async fn notified(&self) {
+        let mut waiter = pin!(Waiter::new(&self.queue, 1));
+        poll_fn(|cx| {
+            waiter.as_mut().poll_with(cx, |queue, _| core::mem::take(&mut queue.state.permit))
+        }).await
+    }
+
+    /// Like `notified`, but gives up after `timeout`.
+    pub(crate) async fn notified_timeout<C: Clock>(&self, timeout: DurationOf<C>) -> Result<(), NotifiedTimeoutErr> {
+        with_timeout(timeout, self.notified()).await.map_err(|TimeoutError| NotifiedTimeoutErr)
+    }
+}
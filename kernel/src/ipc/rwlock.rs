@@ -0,0 +1,101 @@
+use core::cell::UnsafeCell;
+
+use super::{async_mutex::TryLockErr, semaphore::Semaphore};
+
+/// Each reader holds one permit; a writer holds them all.
+const MAX_READERS: usize = Semaphore::MAX_PERMITS;
+
+/// A reader-writer lock for tasks: any number of readers or one writer.
+///
+/// Built on a `Semaphore`, so it is served in order: once a writer waits,
+/// readers arriving after it wait too, and writers are not starved.
+pub(crate) struct RwLock<T: ?Sized> {
+    semaphore: Semaphore,
+    inner: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub(crate) const fn new(inner: T) -> Self {
+        Self {
+            semaphore: Semaphore::new(MAX_READERS),
+            inner: UnsafeCell::new(inner),
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    pub(crate) async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.semaphore.acquire_raw(1).await;
+        RwLockReadGuard { lock: self }
+    }
+
+    pub(crate) async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.semaphore.acquire_raw(MAX_READERS).await;
+        RwLockWriteGuard { lock: self }
+    }
+
+    pub(crate) fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, TryLockErr> {
+        match self.semaphore.try_acquire_raw(1) {
+            true => Ok(RwLockReadGuard { lock: self }),
+            false => Err(TryLockErr),
+        }
+    }
+
+    pub(crate) fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, TryLockErr> {
+        match self.semaphore.try_acquire_raw(MAX_READERS) {
+            true => Ok(RwLockWriteGuard { lock: self }),
+            false => Err(TryLockErr),
+        }
+    }
+}
+
+pub(crate) struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.release_raw(1);
+    }
+}
+
+impl<T: ?Sized> core::ops::Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+pub(crate) struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.release_raw(MAX_READERS);
+    }
+}
+
+impl<T: ?Sized> core::ops::Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<T: ?Sized> core::ops::DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
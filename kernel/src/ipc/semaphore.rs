@@ -0,0 +1,188 @@
+use core::{future::poll_fn, pin::pin};
+
+use alloc::sync::Arc;
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+use super::wait_queue::{WaitList, WaitQueue, WaitState, Waiter};
+
+/// Not enough permits are free, or someone is already waiting for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TryAcquireErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AcquireTimeoutErr;
+
+struct State {
+    permits: usize,
+}
+
+impl State {
+    fn release(queue: &mut WaitList<Self>, permits: usize) {
+        queue.state.permits += permits;
+        Self::dispatch(queue);
+    }
+
+    /// Grants waiters in order for as long as the first one's request fits.
+    fn dispatch(queue: &mut WaitList<Self>) {
+        while let Some(weight) = queue.front_weight().filter(|weight| *weight <= queue.state.permits) {
+            queue.state.permits -= weight;
+            queue.grant_front();
+        }
+    }
+}
+
+impl WaitState for State {
+    fn cancel(queue: &mut WaitList<Self>, weight: usize, granted: bool) {
+        if granted {
+            Self::release(queue, weight);
+        } else {
+            // a large request leaving the front may let smaller ones through
+            Self::dispatch(queue);
+        }
+    }
+}
+
+/// A counting semaphore. Waiters are served strictly in order, so a large
+/// request is not starved by a stream of small ones.
+pub(crate) struct Semaphore {
+    queue: WaitQueue<State>,
+}
+
+impl Semaphore {
+    pub(crate) const MAX_PERMITS: usize = usize::MAX >> 3;
+
+    pub(crate) const fn new(permits: usize) -> Self {
+        assert!(permits <= Self::MAX_PERMITS, "too many permits");
+        Self {
+            queue: WaitQueue::new(State { permits }),
+        }
+    }
+
+    pub(crate) fn available_permits(&self) -> usize {
+        self.queue.lock().state.permits
+    }
+
+    pub(crate) fn add_permits(&self, permits: usize) {
+        State::release(&mut self.queue.lock(), permits);
+    }
+
+    pub(super) async fn acquire_raw(&self, permits: usize) {
+        let mut waiter = pin!(Waiter::new(&self.queue, permits));
+        poll_fn(|cx| {
+            waiter.as_mut().poll_with(cx, |queue, permits| {
+                let free = queue.is_empty() && queue.state.permits >= permits;
+                if free {
+                    queue.state.permits -= permits;
+                }
+                free
+            })
+        }).await
+    }
+
+    pub(super) fn try_acquire_raw(&self, permits: usize) -> bool {
+        let mut queue = self.queue.lock();
+        let free = queue.is_empty() && queue.state.permits >= permits;
+        if free {
+            queue.state.permits -= permits;
+        }
+        free
+    }
+
+    pub(super) fn release_raw(&self, permits: usize) {
+        State::release(&mut self.queue.lock(), permits);
+    }
+
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.acquire_many(1).await
+    }
+
+    /// Waits for `permits` at once. Dropping the future gives up its place in the queue.
+    pub(crate) async fn acquire_many(&self, permits: usize) -> SemaphorePermit<'_> {
+        self.acquire_raw(permits).await;
+        SemaphorePermit { semaphore: self, permits }
+    }
+
+    /// Like `acquire_many`, but gives up after `timeout`.
+    pub(crate) async fn acquire_timeout<C: Clock>(&self, permits: usize, timeout: DurationOf<C>) -> Result<SemaphorePermit<'_>, AcquireTimeoutErr> {
+        with_timeout(timeout, self.acquire_many(permits)).await.map_err(|TimeoutError| AcquireTimeoutErr)
+    }
+
+    pub(crate) fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireErr> {
+        self.try_acquire_many(1)
+    }
+
+    pub(crate) fn try_acquire_many(&self, permits: usize) -> Result<SemaphorePermit<'_>, TryAcquireErr> {
+        match self.try_acquire_raw(permits) {
+            true => Ok(SemaphorePermit { semaphore: self, permits }),
+            false => Err(TryAcquireErr),
+        }
+    }
+
+    /// Like `acquire_many`, with a permit that keeps the semaphore alive and
+    /// can be moved into another task.
+    pub(crate) async fn acquire_owned(self: Arc<Self>, permits: usize) -> OwnedSemaphorePermit {
+        self.acquire_raw(permits).await;
+        OwnedSemaphorePermit { semaphore: self, permits }
+    }
+
+    pub(crate) fn try_acquire_owned(self: Arc<Self>, permits: usize) -> Result<OwnedSemaphorePermit, TryAcquireErr> {
+        match self.try_acquire_raw(permits) {
+            true => Ok(OwnedSemaphorePermit { semaphore: self, permits }),
+            false => Err(TryAcquireErr),
+        }
+    }
+}
+
+/// Permits borrowed from a `Semaphore`, given back on drop.
+pub(crate) struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    pub(crate) fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Drops the permits without giving them back.
+    pub(crate) fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.release_raw(self.permits);
+        }
+    }
+}
+
+/// Permits held through an `Arc<Semaphore>`, given back on drop.
+pub(crate) struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+    permits: usize,
+}
+
+impl OwnedSemaphorePermit {
+    pub(crate) fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    pub(crate) fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+
+    /// Drops the permits without giving them back.
+    pub(crate) fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.release_raw(self.permits);
+        }
+    }
+}
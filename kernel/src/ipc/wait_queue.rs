@@ -0,0 +1,221 @@
+//! Intrusive FIFO queue of waiting tasks, shared by the ipc locks.
+//!
+//! Each waiter's node lives inside its own pinned future, so waiting
+//! allocates nothing, and the future unlinks the node when it is dropped, so
+//! cancelled waiters never linger. A primitive keeps its own state `S` under
+//! the same spin lock as the queue; releasing the resource hands it straight
+//! to the first waiter (`grant_front`) rather than letting a newcomer barge
+//! in, which keeps the order strictly first come, first served.
+
+use core::{cell::UnsafeCell, marker::PhantomPinned, pin::Pin, ptr, task::{Context, Poll, Waker}};
+
+use spin::{Mutex, MutexGuard};
+
+/// Hooks a primitive's state gives the queue.
+pub(crate) trait WaitState: Sized {
+    /// A waiter wanting `weight` was dropped before it finished. With
+    /// `granted` the resource had already been handed to it by `grant_front`
+    /// and must be passed on; otherwise it just left the queue, which may let
+    /// the waiters behind it proceed. A waiter released by `grant_all` has
+    /// nothing to pass on and is not reported.
+    fn cancel(queue: &mut WaitList<Self>, weight: usize, granted: bool);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Idle,
+    Queued,
+    /// Handed the resource by `grant_front`, but not polled since.
+    Granted,
+    /// Released by `grant_all` along with every other waiter, but not polled since.
+    Released,
+    Done,
+}
+
+struct Node {
+    prev: *mut Node,
+    next: *mut Node,
+    waker: Option<Waker>,
+    weight: usize,
+    status: Status,
+}
+
+/// The queue and its primitive's state, reached through `WaitQueue::lock`.
+pub(crate) struct WaitList<S> {
+    pub(crate) state: S,
+    head: *mut Node,
+    tail: *mut Node,
+    len: usize,
+}
+
+// the nodes are only touched with the spin lock held
+unsafe impl<S: Send> Send for WaitList<S> {}
+
+impl<S> WaitList<S> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.is_null()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The weight the first waiter asks for.
+    pub(crate) fn front_weight(&self) -> Option<usize> {
+        // SAFETY: queued nodes stay pinned in their futures until unlinked
+        unsafe { self.head.as_ref().map(|node| node.weight) }
+    }
+
+    /// Removes the first waiter and wakes it, the resource now being its own.
+    pub(crate) fn grant_front(&mut self) -> bool {
+        self.wake_front(Status::Granted)
+    }
+
+    /// Releases every waiter, in order. What they were waiting for is shared,
+    /// so one dropped before it ran has nothing to pass on.
+    pub(crate) fn grant_all(&mut self) {
+        while self.wake_front(Status::Released) {}
+    }
+
+    fn wake_front(&mut self, status: Status) -> bool {
+        let node = self.head;
+        if node.is_null() {
+            return false;
+        }
+        unsafe {
+            self.unlink(node);
+            (*node).status = status;
+            if let Some(waker) = (*node).waker.take() {
+                waker.wake();
+            }
+        }
+        true
+    }
+
+    unsafe fn push_back(&mut self, node: *mut Node) {
+        (*node).prev = self.tail;
+        (*node).next = ptr::null_mut();
+        match self.tail.as_mut() {
+            Some(tail) => tail.next = node,
+            None => self.head = node,
+        }
+        self.tail = node;
+        self.len += 1;
+        (*node).status = Status::Queued;
+    }
+
+    unsafe fn unlink(&mut self, node: *mut Node) {
+        let (prev, next) = ((*node).prev, (*node).next);
+        match prev.as_mut() {
+            Some(prev) => prev.next = next,
+            None => self.head = next,
+        }
+        match next.as_mut() {
+            Some(next) => next.prev = prev,
+            None => self.tail = prev,
+        }
+        (*node).prev = ptr::null_mut();
+        (*node).next = ptr::null_mut();
+        self.len -= 1;
+        (*node).status = Status::Idle;
+    }
+}
+
+pub(crate) struct WaitQueue<S> {
+    list: Mutex<WaitList<S>>,
+}
+
+unsafe impl<S: Send> Sync for WaitQueue<S> {}
+
+impl<S> WaitQueue<S> {
+    pub(crate) const fn new(state: S) -> Self {
+        Self {
+            list: Mutex::new(WaitList {
+                state,
+                head: ptr::null_mut(),
+                tail: ptr::null_mut(),
+                len: 0,
+            }),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, WaitList<S>> {
+        self.list.lock()
+    }
+}
+
+/// One waiter's place in a `WaitQueue`; poll it from the primitive's future.
+pub(crate) struct Waiter<'a, S: WaitState> {
+    queue: &'a WaitQueue<S>,
+    node: UnsafeCell<Node>,
+    _pin: PhantomPinned,
+}
+
+unsafe impl<S: WaitState + Send> Send for Waiter<'_, S> {}
+
+impl<'a, S: WaitState> Waiter<'a, S> {
+    pub(crate) fn new(queue: &'a WaitQueue<S>, weight: usize) -> Self {
+        Self {
+            queue,
+            node: UnsafeCell::new(Node {
+                prev: ptr::null_mut(),
+                next: ptr::null_mut(),
+                waker: None,
+                weight,
+                status: Status::Idle,
+            }),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Ready once the waiter was granted, or when `acquire` succeeds on the
+    /// first poll; otherwise queues it. `acquire` runs with the queue locked
+    /// and should only succeed when nobody is queued ahead.
+    pub(crate) fn poll_with(self: Pin<&mut Self>, cx: &mut Context<'_>, acquire: impl FnOnce(&mut WaitList<S>, usize) -> bool) -> Poll<()> {
+        let mut list = self.queue.lock();
+        let node = self.node.get();
+        // SAFETY: the node is pinned and only touched with the list locked
+        unsafe {
+            match (*node).status {
+                Status::Granted | Status::Released | Status::Done => {
+                    (*node).status = Status::Done;
+                    Poll::Ready(())
+                }
+                Status::Queued => {
+                    if !(*node).waker.as_ref().is_some_and(|waker| waker.will_wake(cx.waker())) {
+                        (*node).waker = Some(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+                Status::Idle => {
+                    if acquire(&mut list, (*node).weight) {
+                        (*node).status = Status::Done;
+                        Poll::Ready(())
+                    } else {
+                        (*node).waker = Some(cx.waker().clone());
+                        list.push_back(node);
+                        Poll::Pending
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: WaitState> Drop for Waiter<'_, S> {
+    fn drop(&mut self) {
+        let node = self.node.get_mut() as *mut Node;
+        let mut list = self.queue.lock();
+        // SAFETY: as in `poll_with`
+        unsafe {
+            match (*node).status {
+                Status::Idle | Status::Released | Status::Done => {}
+                Status::Queued => {
+                    list.unlink(node);
+                    S::cancel(&mut list, (*node).weight, false);
+                }
+                Status::Granted => S::cancel(&mut list, (*node).weight, true),
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Single-value watch channel: holds the latest value and wakes every
+//! receiver when it changes.
+//!
+//! Receivers only ever see the newest value; intermediate ones sent while a
+//! receiver was busy are skipped, which suits state like configuration or
+//! the latest sensor estimate.
+
+use core::{future::poll_fn, task::{Poll, Waker}};
+
+use alloc::{collections::btree_map::BTreeMap, sync::Arc};
+use spin::Mutex;
+
+use crate::time::{clock::Clock, duration::DurationOf, timer::{with_timeout, TimeoutError}};
+
+/// The sender is gone, so the value will not change again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RecvErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChangedTimeoutErr {
+    Timeout,
+    Closed,
+}
+
+struct State<T> {
+    value: T,
+    /// Bumped on every send.
+    version: u64,
+    closed: bool,
+    receivers: usize,
+    next_receiver: usize,
+    waiters: BTreeMap<usize, Waker>,
+}
+
+impl<T> State<T> {
+    fn wake_all(&mut self) {
+        for (_, waker) in core::mem::take(&mut self.waiters) {
+            waker.wake();
+        }
+    }
+}
+
+pub(crate) struct Sender<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Replaces the value and wakes every receiver, even if nobody is subscribed yet.
+    pub(crate) fn send(&self, value: T) {
+        self.send_modify(|current| *current = value);
+    }
+
+    /// Changes the value in place and wakes every receiver.
+    pub(crate) fn send_modify(&self, modify: impl FnOnce(&mut T)) {
+        let mut state = self.state.lock();
+        modify(&mut state.value);
+        state.version += 1;
+        state.wake_all();
+    }
+
+    /// Calls `f` with the current value; keep it short, the channel is locked meanwhile.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.state.lock().value)
+    }
+
+    /// A new receiver, which treats the current value as already seen.
+    pub(crate) fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.state.lock();
+        let version = state.version;
+        Receiver::register(&self.state, &mut state, version)
+    }
+
+    pub(crate) fn receiver_count(&self) -> usize {
+        self.state.lock().receivers
+    }
+}
+
+impl<T: Clone> Sender<T> {
+    pub(crate) fn get(&self) -> T {
+        self.with(T::clone)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        state.wake_all();
+    }
+}
+
+pub(crate) struct Receiver<T> {
+    state: Arc<Mutex<State<T>>>,
+    id: usize,
+    /// The version this receiver last marked as seen.
+    seen: u64,
+}
+
+impl<T> Receiver<T> {
+    fn register(shared: &Arc<Mutex<State<T>>>, state: &mut State<T>, seen: u64) -> Self {
+        let id = state.next_receiver;
+        state.next_receiver += 1;
+        state.receivers += 1;
+        Self { state: shared.clone(), id, seen }
+    }
+
+    /// Calls `f` with the current value without marking it seen; keep it short,
+    /// the channel is locked meanwhile.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.state.lock().value)
+    }
+
+    /// Like `with`, and marks the value seen.
+    pub(crate) fn with_and_update<R>(&mut self, f: impl FnOnce(&T) -> R) -> R {
+        let state = self.state.lock();
+        self.seen = state.version;
+        f(&state.value)
+    }
+
+    /// Whether a value was sent since this receiver last marked one seen.
+    pub(crate) fn has_changed(&self) -> bool {
+        self.state.lock().version != self.seen
+    }
+
+    /// Waits until a value newer than the last one seen is sent, and marks it seen.
+    ///
+    /// Fails once the sender is gone and no unseen value is left.
+    pub(crate) async fn changed(&mut self) -> Result<(), RecvErr> {
+        poll_fn(|cx| {
+            let mut state = self.state.lock();
+            if state.version != self.seen {
+                self.seen = state.version;
+                Poll::Ready(Ok(()))
+            } else if state.closed {
+                Poll::Ready(Err(RecvErr))
+            } else {
+                state.waiters.insert(self.id, cx.waker().clone());
+                Poll::Pending
+            }
+        }).await
+    }
+
+    /// Like `changed`, but gives up after `timeout`.
+    pub(crate) async fn changed_timeout<C: Clock>(&mut self, timeout: DurationOf<C>) -> Result<(), ChangedTimeoutErr> {
+        match with_timeout(timeout, self.changed()).await {
+            Ok(res) => res.map_err(|RecvErr| ChangedTimeoutErr::Closed),
+            Err(TimeoutError) => Err(ChangedTimeoutErr::Timeout),
+        }
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    pub(crate) fn get(&self) -> T {
+        self.with(T::clone)
+    }
+
+    pub(crate) fn get_and_update(&mut self) -> T {
+        self.with_and_update(T::clone)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    /// A receiver that has seen the same value as this one.
+    fn clone(&self) -> Self {
+        let mut state = self.state.lock();
+        Self::register(&self.state, &mut state, self.seen)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.receivers -= 1;
+        state.waiters.remove(&self.id);
+    }
+}
+
+/// A watch channel starting at `init`, which its first receiver has already seen.
+pub(crate) fn watch<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let state = Arc::new(Mutex::new(State {
+        value: init,
+        version: 0,
+        closed: false,
+        receivers: 0,
+        next_receiver: 0,
+        waiters: BTreeMap::new(),
+    }));
+    let receiver = Receiver::register(&state, &mut state.lock(), 0);
+    (Sender { state }, receiver)
+}
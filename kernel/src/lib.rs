@@ -1,11 +1,12 @@
-#![no_std]
+#![cfg_attr(not(feature = "sim"), no_std)]
 #![no_main]
 
 extern crate alloc;
 
+#[cfg(not(feature = "sim"))]
 use core::panic::PanicInfo;
 use allocator::LockedHeap;
-use task::executor::Executor;
+use task::{executor::Executor, Priority};
 
 #[allow(unused_imports)]
 use alloc::{boxed::Box, sync::Arc};
@@ -18,6 +19,7 @@ mod gsh;
 mod fatfs;
 mod time;
 mod ipc;
+mod cron;
 
 #[macro_use]
 mod driver;
@@ -25,11 +27,19 @@ mod driver;
 #[macro_use]
 mod log;
 
+#[cfg(feature = "sim")]
+pub mod sim;
 
+#[cfg(feature = "sim")]
+pub mod check;
 
-#[global_allocator]
+
+
+// the simulator keeps the host allocator, which std needs before `kernel_main` runs
+#[cfg_attr(not(feature = "sim"), global_allocator)]
 pub(crate) static ALLOCATOR: LockedHeap = LockedHeap::empty();
 
+#[cfg(not(feature = "sim"))]
 #[no_mangle]
 pub extern "Rust" fn __rust_alloc_error_handler(_size: usize, _align: usize) -> ! {
     error!("alloc error");
@@ -40,11 +50,13 @@ pub extern "Rust" fn __rust_alloc_error_handler(_size: usize, _align: usize) ->
     }
 }
 
+#[cfg(not(feature = "sim"))]
 #[no_mangle]
 static __rust_no_alloc_shim_is_unstable: u8 = 0;
 
 
 // panic handler
+#[cfg(not(feature = "sim"))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     error!("{}", info);
@@ -64,15 +76,23 @@ pub extern "C" fn kernel_main(sdram_start: *mut u8, sdram_size: usize) -> ! {
     }
     info!("kernel heap was inited");
 
+    time::rtc::init();
+
     let executor = Arc::new(Executor::new());
 
-    let (tx1, rx1) = futures_channel::oneshot::channel::<()>();
-    let (tx2, rx2) = futures_channel::oneshot::channel::<()>();
-    executor.spawn(fatfs::fs_init(tx1, tx2));
-    executor.spawn(fatfs::fs_test1(rx1));
-    executor.spawn(fatfs::fs_test2(rx2));
+    let fs_init = executor.spawn_named("fs_init", fatfs::fs_init());
+    let fs_executor = executor.clone();
+    executor.spawn_named("fs_start", async move {
+        if fs_init.await.is_err() {
+            error!("fs_init was cancelled");
+            return;
+        }
+        fs_executor.spawn_with_priority("fs_test1", fatfs::fs_test1(), Priority::Low).detach();
+        fs_executor.spawn_with_priority("fs_test2", fatfs::fs_test2(), Priority::Low).detach();
+    }).detach();
 
-    executor.spawn(gsh::gshell(executor.clone()));
+    executor.spawn_named("cron", cron::service()).detach();
+    executor.spawn_with_priority("gshell", gsh::gshell(executor.clone()), Priority::High).detach();
 
     executor.run();
 }
@@ -0,0 +1,339 @@
+//! Host simulator for the board side of `c_api`.
+//!
+//! Built with the `sim` feature, the kernel runs as a Linux process: the UART
+//! is stdin/stdout, the SD card is a disk image file and SysTick is a host
+//! thread ticking at `TICK_HZ`, in step with the host clock. That thread
+//! plays the role of the interrupt controller: on every tick it runs
+//! `sys_tick_handler`, completes finished SD transfers through
+//! `io_req_cplt_callback` and feeds received bytes to `usart_add_code`, then
+//! wakes the core from `enter_sleep_mode`. A second
+//! one stands in for TIM2, counting host microseconds for the
+//! high-resolution clock and raising its alarm and half-period interrupts.
+//! The RTC follows the host's clock, shifted by whatever `date set` asked for.
+//!
+//! `disable_irq`/`enable_irq` and `irq_save`/`irq_restore` are no-ops:
+//! handlers run on their own host thread, and the state they share with the
+//! kernel is already atomic or behind spin locks.
+
+extern crate std;
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    string::String,
+    sync::{Condvar, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    c_api::RtcTime,
+    driver::{sdmmc::{io_req_cplt_callback, READ_REQUEST, WRITE_REQUEST}, usart::usart_add_code},
+    time::{system_time::{CalendarTime, SystemTime}, hires::{hires_alarm_handler, hires_period_handler, HIRES_HZ}, sys_tick_handler, TICK_HZ},
+};
+
+const BLOCK_SIZE: u64 = 512;
+const DEFAULT_DISK_SIZE: u64 = 512 * 1024 * 1024;
+const HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+static DISK: OnceLock<Mutex<File>> = OnceLock::new();
+/// SD transfers done on the host, waiting for the next "interrupt" to be reported.
+static IO_DONE: Mutex<Vec<(u32, usize)>> = Mutex::new(Vec::new());
+static UART_RX: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+/// Set by the interrupt thread, cleared when `enter_sleep_mode` returns.
+static IRQ_PENDING: Mutex<bool> = Mutex::new(false);
+static IRQ_SIGNAL: Condvar = Condvar::new();
+static HIRES_EPOCH: OnceLock<Instant> = OnceLock::new();
+/// Compare value of the armed high-resolution alarm.
+static HIRES_ALARM: Mutex<Option<u32>> = Mutex::new(None);
+static HIRES_ALARM_SIGNAL: Condvar = Condvar::new();
+/// Milliseconds the RTC runs ahead of the host clock.
+static RTC_OFFSET: Mutex<i64> = Mutex::new(0);
+
+/// Boots `kernel_main` on the host with `disk_image` as the SD card.
+///
+/// The image is created with a default size if it does not exist yet; the
+/// kernel formats it on first boot.
+pub fn boot(disk_image: &str) -> ! {
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(disk_image)
+        .unwrap_or_else(|err| panic!("cannot open disk image {}: {}", disk_image, err));
+    if disk.metadata().expect("disk image metadata").len() == 0 {
+        disk.set_len(DEFAULT_DISK_SIZE).expect("cannot size disk image");
+    }
+    DISK.set(Mutex::new(disk)).expect("simulator booted twice");
+
+    HIRES_EPOCH.get_or_init(Instant::now);
+    thread::spawn(uart_rx_thread);
+    thread::spawn(interrupt_thread);
+    thread::spawn(hires_timer_thread);
+
+    let heap: &'static mut [u8] = vec![0u8; HEAP_SIZE].leak();
+    crate::kernel_main(heap.as_mut_ptr(), heap.len())
+}
+
+/// Feeds stdin to the UART, turning line feeds into the carriage return a serial terminal sends.
+fn uart_rx_thread() {
+    let mut stdin = std::io::stdin();
+    let mut buf = [0u8; 64];
+    loop {
+        match stdin.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                let mut rx = UART_RX.lock().unwrap();
+                rx.extend(buf[..n].iter().map(|&b| if b == b'\n' { b'\r' } else { b }));
+            }
+        }
+    }
+}
+
+fn interrupt_thread() {
+    let epoch = *HIRES_EPOCH.get().expect("simulator not booted");
+    let tick_nanos = 1_000_000_000 / TICK_HZ;
+    let mut ticks = 0u64;
+    loop {
+        // sleep until the next tick is due on the host clock, not for a tick
+        // from now, or the time spent handling it would add up to a drift
+        let next = epoch + Duration::from_nanos((ticks + 1) * tick_nanos);
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+
+        // a late wakeup owes every tick since, just as a held-off SysTick catches up
+        let due = epoch.elapsed().as_nanos() as u64 / tick_nanos;
+        while ticks < due {
+            sys_tick_handler();
+            ticks += 1;
+        }
+
+        let done: Vec<(u32, usize)> = IO_DONE.lock().unwrap().drain(..).collect();
+        for (req, end_addr) in done {
+            io_req_cplt_callback(req, end_addr, 0);
+        }
+
+        let rx: Vec<u8> = UART_RX.lock().unwrap().drain(..).collect();
+        for code in rx {
+            usart_add_code(code);
+        }
+
+        raise_irq();
+    }
+}
+
+fn raise_irq() {
+    *IRQ_PENDING.lock().unwrap() = true;
+    IRQ_SIGNAL.notify_all();
+}
+
+/// Host microseconds since boot; stands still at zero, like TIM2 before it is
+/// enabled, when the kernel is not booted, so that under `gungnir-check` only
+/// the virtual clock moves the high-resolution clock.
+fn hires_counter() -> u64 {
+    let Some(epoch) = HIRES_EPOCH.get() else {
+        return 0;
+    };
+    (epoch.elapsed().as_nanos() * HIRES_HZ as u128 / 1_000_000_000) as u64
+}
+
+fn hires_to_host(ticks: u64) -> Duration {
+    Duration::from_nanos((ticks as u128 * 1_000_000_000 / HIRES_HZ as u128) as u64)
+}
+
+/// Raises the half-period interrupts of the 32-bit counter and the compare alarm.
+fn hires_timer_thread() {
+    let mut half_periods = 0u64;
+    let mut alarm = HIRES_ALARM.lock().unwrap();
+    loop {
+        let now = hires_counter();
+        if now >> 31 > half_periods {
+            half_periods += 1;
+            drop(alarm);
+            hires_period_handler();
+            raise_irq();
+            alarm = HIRES_ALARM.lock().unwrap();
+            continue;
+        }
+
+        let mut wait = ((half_periods + 1) << 31) - now;
+        if let Some(compare) = *alarm {
+            let remaining = compare.wrapping_sub(now as u32) as u64;
+            if remaining == 0 || remaining >= 1 << 31 {
+                *alarm = None;
+                drop(alarm);
+                hires_alarm_handler();
+                raise_irq();
+                alarm = HIRES_ALARM.lock().unwrap();
+                continue;
+            }
+            wait = wait.min(remaining);
+        }
+        alarm = HIRES_ALARM_SIGNAL.wait_timeout(alarm, hires_to_host(wait)).unwrap().0;
+    }
+}
+
+fn disk_io(addr: u32, num: u32, op: impl FnOnce(&mut File, usize) -> std::io::Result<()>) -> i32 {
+    let mut disk = DISK.get().expect("simulator not booted").lock().unwrap();
+    let len = (num as u64 * BLOCK_SIZE) as usize;
+    let res = disk
+        .seek(SeekFrom::Start(addr as u64 * BLOCK_SIZE))
+        .and_then(|_| op(&mut disk, len));
+    match res {
+        Ok(()) => 0,
+        Err(err) => {
+            std::eprintln!("sim: disk io at block {} failed: {}", addr, err);
+            -1
+        }
+    }
+}
+
+/// # Safety
+///
+/// `buf` must be valid for writes of `num` blocks.
+#[no_mangle]
+pub unsafe extern "C" fn sdmmc_read_blocks_it(buf: *mut u8, addr: u32, num: u32) -> i32 {
+    let res = disk_io(addr, num, |disk, len| {
+        let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+        disk.read_exact(buf)
+    });
+    if res == 0 {
+        // like the DMA, report the buffer's end address once the "transfer" is over
+        let end_addr = buf as usize + (num as u64 * BLOCK_SIZE) as usize;
+        IO_DONE.lock().unwrap().push((READ_REQUEST, end_addr));
+    }
+    res
+}
+
+/// # Safety
+///
+/// `data` must be valid for reads of `num` blocks.
+#[no_mangle]
+pub unsafe extern "C" fn sdmmc_write_blocks_it(data: *const u8, addr: u32, num: u32) -> i32 {
+    let res = disk_io(addr, num, |disk, len| {
+        let data = unsafe { core::slice::from_raw_parts(data, len) };
+        disk.write_all(data)
+    });
+    if res == 0 {
+        let end_addr = data as usize + (num as u64 * BLOCK_SIZE) as usize;
+        IO_DONE.lock().unwrap().push((WRITE_REQUEST, end_addr));
+    }
+    res
+}
+
+#[no_mangle]
+pub extern "C" fn get_sdcard_capacity() -> u64 {
+    let disk = DISK.get().expect("simulator not booted").lock().unwrap();
+    disk.metadata().map(|meta| meta.len()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn _putchar(ch: u8) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(&[ch]);
+    let _ = stdout.flush();
+}
+
+#[no_mangle]
+pub extern "C" fn get_hires_ticks() -> u32 {
+    hires_counter() as u32
+}
+
+#[no_mangle]
+pub extern "C" fn set_hires_alarm(ticks: u32) {
+    *HIRES_ALARM.lock().unwrap() = Some(ticks);
+    HIRES_ALARM_SIGNAL.notify_all();
+}
+
+#[no_mangle]
+pub extern "C" fn cancel_hires_alarm() {
+    *HIRES_ALARM.lock().unwrap() = None;
+}
+
+fn host_unix_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since| since.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn rtc_init() -> i32 {
+    0
+}
+
+/// # Safety
+///
+/// `time` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rtc_get(time: *mut RtcTime) -> i32 {
+    let millis = host_unix_millis() + *RTC_OFFSET.lock().unwrap();
+    let now = SystemTime::from_unix_millis(millis.max(0) as u64).to_calendar();
+    unsafe {
+        *time = RtcTime {
+            year: now.year,
+            month: now.month,
+            day: now.day,
+            hour: now.hour,
+            minute: now.minute,
+            second: now.second,
+            weekday: now.weekday(),
+            millis: now.millis,
+        };
+    }
+    0
+}
+
+/// # Safety
+///
+/// `time` must be valid for reads.
+#[no_mangle]
+pub unsafe extern "C" fn rtc_set(time: *const RtcTime) -> i32 {
+    let time = unsafe { *time };
+    match CalendarTime::new(time.year, time.month, time.day, time.hour, time.minute, time.second, time.millis) {
+        Some(time) => {
+            *RTC_OFFSET.lock().unwrap() = SystemTime::from(time).as_unix_millis() as i64 - host_unix_millis();
+            0
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn enter_sleep_mode() {
+    let mut pending = IRQ_PENDING.lock().unwrap();
+    while !*pending {
+        pending = IRQ_SIGNAL.wait(pending).unwrap();
+    }
+    *pending = false;
+}
+
+#[no_mangle]
+pub extern "C" fn enable_irq() {}
+
+#[no_mangle]
+pub extern "C" fn disable_irq() {}
+
+#[no_mangle]
+pub extern "C" fn irq_save() -> u32 {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn irq_restore(_primask: u32) {}
+
+#[no_mangle]
+pub extern "C" fn led_toggle() {}
+
+#[no_mangle]
+pub extern "C" fn led_twinkle(ms: u32) {
+    thread::sleep(Duration::from_millis(ms as u64));
+}
+
+/// Parses the simulator's command line: `gungnir-sim [disk image]`.
+pub fn disk_image_arg() -> String {
+    std::env::args().nth(1).unwrap_or_else(|| String::from("gungnir.img"))
+}
@@ -1,30 +1,37 @@
 #[allow(unused_imports)]
-use crate::{c_api::enable_irq, println};
+use crate::{c_api::{disable_irq, enable_irq}, println};
 
-use super::{Task, TaskId};
-use alloc::{collections::BTreeMap, sync::Arc, task::Wake};
-use core::{task::{Context, Poll, Waker}, future::Future};
-use crossbeam_queue::ArrayQueue;
+use super::{idle::{IdleHook, WfiIdle}, isr, join_handle::{JoinError, JoinHandle, JoinState}, run_queue::{QueueState, RunQueue}, ExecutorStats, Priority, Task, TaskId, TaskInfo};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, task::Wake, vec::Vec};
+use core::{task::{Context, Poll, Waker}, future::Future, sync::atomic::{AtomicU32, Ordering}};
 use spin::Mutex;
 
-use crate::{debug, log};
+use crate::{debug, log, time::{hires::{HiResDuration, HiResInstant}, instant::Instant}};
 
 
 struct TaskWaker {
     task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    priority: Priority,
+    last_wake: Arc<AtomicU32>,
+    queued: Arc<QueueState>,
+    task_queue: Arc<RunQueue>,
 }
 
 impl TaskWaker {
-    fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
+    fn new(task: &Task, task_queue: Arc<RunQueue>) -> Waker {
         Waker::from(Arc::new(TaskWaker {
-            task_id,
+            task_id: task.id,
+            priority: task.priority,
+            last_wake: task.last_wake.clone(),
+            queued: task.queued.clone(),
             task_queue,
         }))
     }
     
     fn wake_task(&self) {
-        self.task_queue.push(self.task_id).expect("task_queue full");
+        // zero is reserved for "never woken"
+        self.last_wake.store((Instant::now().as_ticks() as u32).max(1), Ordering::Relaxed);
+        self.task_queue.schedule(self.task_id, self.priority, &self.queued);
     }
 }
 
@@ -38,98 +45,258 @@ impl Wake for TaskWaker {
     }
 }
 
+struct Counters {
+    since: Instant,
+    polls: u64,
+    busy: HiResDuration,
+    idle: HiResDuration,
+}
+
 pub(crate) struct Executor {
     tasks: Mutex<BTreeMap<TaskId, Task>>,
     tmp_task: Mutex<BTreeMap<TaskId, Task>>,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    task_queue: Arc<RunQueue>,
     waker_cache: Mutex<BTreeMap<TaskId, Waker>>,
+    abort_list: Arc<Mutex<Vec<TaskId>>>,
+    current: Mutex<Option<TaskInfo>>,
+    idle_hook: Box<dyn IdleHook>,
+    counters: Mutex<Counters>,
 }
 
 impl Executor {
     pub(crate) fn new() -> Self {
+        Self::with_idle_hook(Box::new(WfiIdle))
+    }
+
+    pub(crate) fn with_idle_hook(idle_hook: Box<dyn IdleHook>) -> Self {
+        isr::init();
         Executor {
             tasks: Mutex::new(BTreeMap::new()),
             tmp_task: Mutex::new(BTreeMap::new()),
-            task_queue: Arc::new(ArrayQueue::new(100)),
+            task_queue: Arc::new(RunQueue::new(100)),
             waker_cache: Mutex::new(BTreeMap::new()),
+            abort_list: Arc::new(Mutex::new(Vec::new())),
+            current: Mutex::new(None),
+            idle_hook,
+            counters: Mutex::new(Counters {
+                since: Instant::now(),
+                polls: 0,
+                busy: HiResDuration::from_ticks(0),
+                idle: HiResDuration::from_ticks(0),
+            }),
         }
     }
 
-    pub(crate) fn spawn<F>(&self, task: F)
+    #[allow(dead_code)]
+    pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where 
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        self.spawn_with_priority("anonymous", future, Priority::default())
+    }
+
+    pub(crate) fn spawn_named<F>(&self, name: &'static str, future: F) -> JoinHandle<F::Output>
     where 
-        F: Future<Output = ()> + 'static,
+        F: Future + 'static,
+        F::Output: 'static,
     {
-        let task = Task::new(task);
+        self.spawn_with_priority(name, future, Priority::default())
+    }
+
+    pub(crate) fn spawn_with_priority<F>(&self, name: &'static str, future: F, priority: Priority) -> JoinHandle<F::Output>
+    where 
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let state = Arc::new(JoinState::new());
+        let task_state = state.clone();
+        let task = Task::new(name, async move {
+            task_state.complete(future.await);
+        }, priority, state.clone());
         let task_id = task.id;
         if self.tmp_task.lock().insert(task_id, task).is_some() {
             panic!("task with same ID already in tasks");
         }
+        JoinHandle::new(task_id, state, self.abort_list.clone())
+    }
+
+    /// Drops the task's future on the next scheduling round; its joiner sees `JoinError::Cancelled`.
+    ///
+    /// Deferred rather than immediate so that a task may abort itself or others while being polled.
+    #[allow(dead_code)]
+    pub(crate) fn abort(&self, task_id: TaskId) {
+        self.abort_list.lock().push(task_id);
+    }
+
+    /// Lists all live tasks, including the one currently being polled.
+    pub(crate) fn task_infos(&self) -> Vec<TaskInfo> {
+        let mut infos: Vec<TaskInfo> = self.tasks.lock().values().map(Task::info).collect();
+        infos.extend(self.tmp_task.lock().values().map(Task::info));
+        infos.extend(*self.current.lock());
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
+
+    pub(crate) fn stat(&self) -> ExecutorStats {
+        let counters = self.counters.lock();
+        ExecutorStats {
+            since: counters.since,
+            polls: counters.polls,
+            wakeups: self.task_queue.wakeups(),
+            queue_high_water: self.task_queue.high_water(),
+            busy: counters.busy,
+            idle: counters.idle,
+        }
+    }
+
+    fn abort_tasks(&self) {
+        let Self {tasks, tmp_task, waker_cache, abort_list, ..} = self;
+
+        while let Some(task_id) = abort_list.lock().pop() {
+            let task = tmp_task.lock().remove(&task_id)
+                .or_else(|| tasks.lock().remove(&task_id));
+            if let Some(task) = task {
+                debug!("[-Executor-]: {:?} Cancelled", task_id);
+                waker_cache.lock().remove(&task_id);
+                task.cancel();
+            }
+        }
     }
 
     fn run_ready_tasks(&self) {
-        let Self {tasks, task_queue, waker_cache, tmp_task} = self;
+        self.abort_tasks();
+        isr::drain(|task_id| {
+            if let Some(waker) = self.waker_cache.lock().get(&task_id) {
+                waker.wake_by_ref();
+            }
+        });
+        crate::ipc::event_group::drain_isr_pending();
+
+        let Self {tasks, task_queue, waker_cache, tmp_task, current, ..} = self;
 
         while let Some((task_id, task)) = tmp_task.lock().pop_first() {
-            task_queue.push(task_id).expect("task queue full");
+            task_queue.schedule(task_id, task.priority, &task.queued);
             if tasks.lock().insert(task_id, task).is_some() {
                 panic!("task with same ID already in tasks");
             }
         }
         
-        while let Some(task_id) = task_queue.pop() {
-            let mut tasks_guard = tasks.lock();
-            let task = match tasks_guard.get_mut(&task_id) {
-                Some(task) => task,
-                None => continue,
-            };
-
-            let mut waker_cache_guard = waker_cache.lock();
-            let waker = waker_cache_guard
-                                    .entry(task_id)
-                                    .or_insert_with(|| {
-                                        TaskWaker::new(task_id, task_queue.clone())
-                                    });
-            let mut context = Context::from_waker(waker);
-
-            match task.poll(&mut context) {
-                Poll::Ready(_result) => {
-                    debug!("[-Executor-]: {:?} Completed", task_id);
-                    tasks_guard.remove(&task_id);
-                    waker_cache_guard.remove(&task_id);
+        loop {
+            while let Some(task_id) = task_queue.pop() {
+                // taken out of the map while polled, so the task itself may query the executor
+                let mut task = match tasks.lock().remove(&task_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                task.queued.clear();
+
+                let waker = waker_cache.lock()
+                                        .entry(task_id)
+                                        .or_insert_with(|| {
+                                            TaskWaker::new(&task, task_queue.clone())
+                                        })
+                                        .clone();
+                let mut context = Context::from_waker(&waker);
+
+                *current.lock() = Some(task.info());
+                let res = task.poll(&mut context);
+                *current.lock() = None;
+                self.counters.lock().polls += 1;
+
+                match res {
+                    Poll::Ready(_result) => {
+                        debug!("[-Executor-]: {:?} Completed", task_id);
+                        waker_cache.lock().remove(&task_id);
+                    }
+                    Poll::Pending => {
+                        tasks.lock().insert(task_id, task);
+                    }
                 }
-                Poll::Pending => {}
+            }
+
+            if !task_queue.take_overflow() {
+                break;
+            }
+
+            for task in tasks.lock().values() {
+                task_queue.requeue(task.id, task.priority, &task.queued);
             }
         }
     }
 
     fn do_idle(&self) {
         use super::yield_now::YIELD_LIST;
-        use crate::time::{timer::WAITING_TIMERS, instant::Instant};
+        use crate::time::{hires, timer_queue::TIMER_QUEUE};
 
         while let Some((waker, waked)) = YIELD_LIST.lock().pop() {
             waked.store(true, core::sync::atomic::Ordering::Release);
             waker.wake();
         }
 
-        let now = Instant::now();
-        WAITING_TIMERS.lock().retain(|(expires_at, waker)| {
-            if *expires_at <= now {
-                waker.wake();
-                false
-            } else {
-                true
-            }
-        });
+        let mut expired = TIMER_QUEUE.lock().take_expired(Instant::now());
+        expired.extend(hires::take_expired());
+        for waker in expired {
+            waker.wake();
+        }
 
+        // with interrupts masked, a wakeup between this check and the sleep
+        // leaves its interrupt pending, which ends the sleep immediately
+        unsafe {
+            disable_irq();
+        }
+        if !self.has_ready_tasks() && YIELD_LIST.lock().is_empty() {
+            let deadline = TIMER_QUEUE.lock().next_deadline();
+            if deadline.is_none_or(|deadline| deadline > Instant::now()) {
+                self.idle_hook.sleep(deadline);
+            }
+        }
         unsafe {
             enable_irq();
         }
     }
 
+    fn has_ready_tasks(&self) -> bool {
+        !self.task_queue.is_empty()
+            || isr::has_pending()
+            || crate::time::hires::has_pending()
+            || crate::ipc::event_group::has_isr_pending()
+            || !self.tmp_task.lock().is_empty()
+            || !self.abort_list.lock().is_empty()
+    }
+
+    /// Runs the executor until `future` completes, returning its output.
+    ///
+    /// Tasks spawned before or meanwhile run alongside it and are left pending afterwards.
+    #[allow(dead_code)]
+    pub(crate) fn block_on<F>(&self, future: F) -> Result<F::Output, JoinError>
+    where 
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let mut handle = self.spawn_named("block_on", future);
+        loop {
+            self.run_ready_tasks();
+            if let Some(output) = handle.try_join() {
+                return output;
+            }
+            self.do_idle();
+        }
+    }
+
     pub(crate) fn run(&self) -> ! {
+        let mut last = HiResInstant::now();
         loop {
             self.run_ready_tasks();
+            let polled = HiResInstant::now();
             self.do_idle();
+            let idled = HiResInstant::now();
+
+            let mut counters = self.counters.lock();
+            counters.busy += polled.saturating_duration_since(last);
+            counters.idle += idled.saturating_duration_since(polled);
+            drop(counters);
+            last = idled;
         }
     }
 }
\ No newline at end of file
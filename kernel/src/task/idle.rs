@@ -0,0 +1,25 @@
+use crate::{c_api::enter_sleep_mode, time::instant::Instant};
+
+/// What the executor does when no task is ready to run.
+///
+/// `sleep` is called with interrupts disabled, after the run queue was found
+/// empty. It must return once an interrupt is pending or `deadline` (the next
+/// timer expiry, if any) has been reached; the executor re-enables interrupts
+/// afterwards so that the pending handler runs before tasks are polled again.
+pub(crate) trait IdleHook {
+    fn sleep(&self, deadline: Option<Instant>);
+}
+
+/// Halts the core with WFI until the next interrupt.
+///
+/// The SysTick interrupt fires every tick, so timer deadlines are never
+/// overslept by more than one tick.
+pub(crate) struct WfiIdle;
+
+impl IdleHook for WfiIdle {
+    fn sleep(&self, _deadline: Option<Instant>) {
+        unsafe {
+            enter_sleep_mode();
+        }
+    }
+}
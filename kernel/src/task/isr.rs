@@ -0,0 +1,98 @@
+//! Generic bridge from C interrupt handlers to Rust tasks.
+//!
+//! ISRs only touch atomics and a preallocated lock-free queue here, never the
+//! allocator or a lock. The executor drains both pending sets before polling
+//! and before going to sleep. C code gets the prototypes from `board/inc/isr.h`:
+//!
+//! ```c
+//! extern int isr_post_event(uint32_t event);
+//! extern int isr_wake_task(uint32_t task_id);
+//! ```
+
+use core::{future::{poll_fn, Future}, sync::atomic::{AtomicU32, Ordering}, task::Poll};
+
+use conquer_once::spin::OnceCell;
+use crossbeam_queue::ArrayQueue;
+use futures_util::task::AtomicWaker;
+
+use super::TaskId;
+
+/// Number of event lines, one bit each in the pending set.
+pub(crate) const MAX_ISR_EVENTS: u32 = 32;
+
+const WAKE_QUEUE_CAP: usize = 32;
+
+/// Events posted by ISRs and not yet drained by the executor.
+static PENDING_EVENTS: AtomicU32 = AtomicU32::new(0);
+/// Events drained by the executor and not yet consumed by a waiter.
+static FIRED_EVENTS: AtomicU32 = AtomicU32::new(0);
+static EVENT_WAKERS: [AtomicWaker; MAX_ISR_EVENTS as usize] = [const { AtomicWaker::new() }; MAX_ISR_EVENTS as usize];
+
+static WAKE_QUEUE: OnceCell<ArrayQueue<u32>> = OnceCell::uninit();
+
+/// Allocates the task wake queue; called once by the executor before interrupts can use it.
+pub(super) fn init() {
+    let _ = WAKE_QUEUE.try_init_once(|| ArrayQueue::new(WAKE_QUEUE_CAP));
+}
+
+/// Marks `event` as pending. Returns 0 on success, -1 if `event` is out of range.
+#[no_mangle]
+pub extern "C" fn isr_post_event(event: u32) -> i32 {
+    if event >= MAX_ISR_EVENTS {
+        return -1;
+    }
+    PENDING_EVENTS.fetch_or(1 << event, Ordering::AcqRel);
+    0
+}
+
+/// Asks the executor to wake the task with raw id `task_id`.
+/// Returns 0 on success, -1 if the wake queue is full or not yet initialized.
+#[no_mangle]
+pub extern "C" fn isr_wake_task(task_id: u32) -> i32 {
+    match WAKE_QUEUE.try_get() {
+        Ok(queue) if queue.push(task_id).is_ok() => 0,
+        _ => -1,
+    }
+}
+
+pub(super) fn has_pending() -> bool {
+    PENDING_EVENTS.load(Ordering::Acquire) != 0
+        || WAKE_QUEUE.try_get().is_ok_and(|queue| !queue.is_empty())
+}
+
+/// Wakes the waiters of every pending event and hands each pending task wake to `wake_task`.
+pub(super) fn drain(mut wake_task: impl FnMut(TaskId)) {
+    let pending = PENDING_EVENTS.swap(0, Ordering::AcqRel);
+    if pending != 0 {
+        FIRED_EVENTS.fetch_or(pending, Ordering::AcqRel);
+        for event in 0..MAX_ISR_EVENTS {
+            if pending & (1 << event) != 0 {
+                EVENT_WAKERS[event as usize].wake();
+            }
+        }
+    }
+
+    if let Ok(queue) = WAKE_QUEUE.try_get() {
+        while let Some(task_id) = queue.pop() {
+            wake_task(TaskId(task_id));
+        }
+    }
+}
+
+/// Waits until an ISR posts `event`, consuming it. Posts made while nobody waits are kept, not counted.
+///
+/// Only one task should wait on a given event at a time.
+#[allow(dead_code)]
+pub(crate) fn wait_event(event: u32) -> impl Future<Output = ()> + Send + Sync + 'static {
+    assert!(event < MAX_ISR_EVENTS, "isr event out of range");
+    let bit = 1 << event;
+    poll_fn(move |cx| {
+        EVENT_WAKERS[event as usize].register(cx.waker());
+        if FIRED_EVENTS.fetch_and(!bit, Ordering::AcqRel) & bit != 0 {
+            EVENT_WAKERS[event as usize].take();
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+}
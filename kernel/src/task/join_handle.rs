@@ -0,0 +1,119 @@
+use core::{future::Future, pin::Pin, sync::atomic::{AtomicBool, Ordering}, task::{Context, Poll}};
+
+use alloc::{sync::Arc, vec::Vec};
+use futures_util::task::AtomicWaker;
+use spin::Mutex;
+
+use super::TaskId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JoinError {
+    Cancelled,
+}
+
+/// Lets the executor report cancellation without knowing the task's output type.
+pub(super) trait Cancel {
+    fn cancel(&self);
+}
+
+pub(super) struct JoinState<T> {
+    finished: AtomicBool,
+    output: Mutex<Option<Result<T, JoinError>>>,
+    waker: AtomicWaker,
+}
+
+impl<T> JoinState<T> {
+    pub(super) fn new() -> Self {
+        Self {
+            finished: AtomicBool::new(false),
+            output: Mutex::new(None),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    pub(super) fn complete(&self, output: T) {
+        self.finish(Ok(output));
+    }
+
+    fn finish(&self, output: Result<T, JoinError>) {
+        *self.output.lock() = Some(output);
+        self.finished.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Cancel for JoinState<T> {
+    fn cancel(&self) {
+        if !self.is_finished() {
+            self.finish(Err(JoinError::Cancelled));
+        }
+    }
+}
+
+/// Handle to a spawned task, resolving to the task's output once it completes.
+///
+/// Dropping the handle does not stop the task; its output is simply discarded.
+pub(crate) struct JoinHandle<T> {
+    id: TaskId,
+    state: Arc<JoinState<T>>,
+    abort_list: Arc<Mutex<Vec<TaskId>>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(super) fn new(id: TaskId, state: Arc<JoinState<T>>, abort_list: Arc<Mutex<Vec<TaskId>>>) -> Self {
+        Self { id, state, abort_list }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn id(&self) -> TaskId {
+        self.id
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_finished(&self) -> bool {
+        self.state.is_finished()
+    }
+
+    /// Requests the executor to drop the task; joiners then get `JoinError::Cancelled`.
+    pub(crate) fn abort(&self) {
+        if !self.is_finished() {
+            self.abort_list.lock().push(self.id);
+        }
+    }
+
+    /// Takes the output if the task has finished, without waiting.
+    pub(crate) fn try_join(&mut self) -> Option<Result<T, JoinError>> {
+        if self.is_finished() {
+            self.state.output.lock().take()
+        } else {
+            None
+        }
+    }
+
+    /// Lets the task run on its own, discarding its output.
+    pub(crate) fn detach(self) {}
+}
+
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if !self.state.is_finished() {
+            self.state.waker.register(cx.waker());
+            if !self.state.is_finished() {
+                return Poll::Pending;
+            }
+        }
+
+        match self.state.output.lock().take() {
+            Some(output) => Poll::Ready(output),
+            None => panic!("{:?} joined after its output was taken", self.id),
+        }
+    }
+}
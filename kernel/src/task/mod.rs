@@ -1,4 +1,7 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, sync::Arc};
+use join_handle::Cancel;
+use run_queue::QueueState;
+use crate::time::{duration::Duration, hires::{HiResDuration, HiResInstant}, instant::Instant};
 use core::{
     future::Future,
     pin::Pin,
@@ -7,32 +10,159 @@ use core::{
 };
 
 pub(crate) mod executor;
+pub(crate) mod idle;
+pub(crate) mod isr;
+pub(crate) mod join_handle;
+pub(crate) mod scope;
+pub(crate) mod run_queue;
 pub(crate) mod yield_now;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct TaskId(u32);
+pub(crate) struct TaskId(u32);
 
 impl TaskId {
     fn new() -> Self {
         static NEXT_ID: AtomicU32 = AtomicU32::new(0);
         TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
+
+    /// The id as passed to `isr_wake_task` from C.
+    #[allow(dead_code)]
+    pub(crate) fn as_raw(&self) -> u32 {
+        self.0
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn from_raw(raw: u32) -> Self {
+        TaskId(raw)
+    }
+}
+
+impl core::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Priority {
+    High = 0,
+    #[default]
+    Normal = 1,
+    Low = 2,
+}
+
+impl Priority {
+    const LEVELS: usize = 3;
+}
+
+impl core::fmt::Display for Priority {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        };
+        f.pad(name)
+    }
+}
+
+/// Snapshot of the executor's counters, as reported by `load`.
+///
+/// `busy` is the time spent polling tasks and `idle` the time spent in the
+/// idle loop, sleeping included, both since `since`. Both are measured on
+/// the high-resolution clock, as a round mostly takes well under a tick.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ExecutorStats {
+    pub(crate) since: Instant,
+    pub(crate) polls: u64,
+    pub(crate) wakeups: u32,
+    pub(crate) queue_high_water: u32,
+    pub(crate) busy: HiResDuration,
+    pub(crate) idle: HiResDuration,
+}
+
+/// Snapshot of a task's bookkeeping, as listed by `ps` and `top`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TaskInfo {
+    pub(crate) id: TaskId,
+    pub(crate) name: &'static str,
+    pub(crate) priority: Priority,
+    pub(crate) spawned_at: Instant,
+    pub(crate) polls: u64,
+    pub(crate) busy: HiResDuration,
+    pub(crate) last_wake: Option<Instant>,
 }
 
 pub(crate) struct Task {
     id: TaskId,
+    name: &'static str,
+    priority: Priority,
+    spawned_at: Instant,
+    polls: u64,
+    /// Time spent in `poll`, on the high-resolution clock since most polls take well under a tick.
+    busy: HiResDuration,
+    /// Low half of the tick of the last wakeup, written by the task's waker; zero until first woken.
+    last_wake: Arc<AtomicU32>,
+    /// Whether the task sits in the run queue, so repeated wakeups enqueue it only once.
+    queued: Arc<QueueState>,
     future: Pin<Box<dyn Future<Output = ()>>>,
+    join: Arc<dyn Cancel>,
 }
 
 impl Task {
-    pub(crate) fn new(future: impl Future<Output = ()> + 'static) -> Task {
+    fn new(
+        name: &'static str,
+        future: impl Future<Output = ()> + 'static,
+        priority: Priority,
+        join: Arc<dyn Cancel>,
+    ) -> Task {
         Task {
             id: TaskId::new(),
+            name,
+            priority,
+            spawned_at: Instant::now(),
+            polls: 0,
+            busy: HiResDuration::from_ticks(0),
+            last_wake: Arc::new(AtomicU32::new(0)),
+            queued: Arc::new(QueueState::new()),
             future: Box::pin(future),
+            join,
         }
     }
 
     fn poll(&mut self, context: &mut Context) -> Poll<()> {
-        self.future.as_mut().poll(context)
+        let start = HiResInstant::now();
+        let res = self.future.as_mut().poll(context);
+        self.polls += 1;
+        self.busy += HiResInstant::now().saturating_duration_since(start);
+        res
+    }
+
+    fn info(&self) -> TaskInfo {
+        let last_wake = match self.last_wake.load(Ordering::Relaxed) {
+            0 => None,
+            ticks => {
+                // only the low half is stored; the wake lies less than 2^32 ticks back
+                let now = Instant::now();
+                let ago = (now.as_ticks() as u32).wrapping_sub(ticks);
+                now.checked_sub(Duration::from_ticks(ago as u64))
+            }
+        };
+        TaskInfo {
+            id: self.id,
+            name: self.name,
+            priority: self.priority,
+            spawned_at: self.spawned_at,
+            polls: self.polls,
+            busy: self.busy,
+            last_wake,
+        }
+    }
+
+    fn cancel(self) {
+        let Task { join, future, .. } = self;
+        drop(future);
+        join.cancel();
     }
 }
\ No newline at end of file
@@ -0,0 +1,126 @@
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+
+use crossbeam_queue::ArrayQueue;
+
+use super::{Priority, TaskId};
+
+/// A ready queue is served ahead of higher-priority ones after being passed over this many times.
+pub(crate) const AGING_LIMIT: u32 = 8;
+
+/// Where a task stands with the run queue, shared by the task and its wakers.
+pub(crate) struct QueueState(AtomicU8);
+
+impl QueueState {
+    const IDLE: u8 = 0;
+    const QUEUED: u8 = 1;
+    /// Woken, but the run queue had no room; the next overflow rescan queues it.
+    const LOST: u8 = 2;
+
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU8::new(Self::IDLE))
+    }
+
+    /// Marks the task as taken off the queue; the next wakeup queues it again.
+    pub(crate) fn clear(&self) {
+        self.0.store(Self::IDLE, Ordering::Release);
+    }
+}
+
+/// Ready queues, one per priority level.
+///
+/// Higher levels are always popped first, except that a non-empty lower level
+/// which has been passed over `AGING_LIMIT` times gets one turn, so that a
+/// steady stream of high-priority wakeups cannot starve it.
+///
+/// A task is queued at most once until it is polled again. Wakeups that do not
+/// fit are never lost: the task is marked as lost and `overflow` is raised,
+/// telling the executor to rescan its tasks and `requeue` the marked ones.
+pub(crate) struct RunQueue {
+    queues: [ArrayQueue<TaskId>; Priority::LEVELS],
+    skipped: [AtomicU32; Priority::LEVELS],
+    overflow: AtomicBool,
+    wakeups: AtomicU32,
+    high_water: AtomicU32,
+}
+
+impl RunQueue {
+    pub(crate) fn new(cap: usize) -> Self {
+        Self {
+            queues: core::array::from_fn(|_| ArrayQueue::new(cap)),
+            skipped: core::array::from_fn(|_| AtomicU32::new(0)),
+            overflow: AtomicBool::new(false),
+            wakeups: AtomicU32::new(0),
+            high_water: AtomicU32::new(0),
+        }
+    }
+
+    /// Queues the task unless its state says it already is. Safe to call from interrupts.
+    pub(crate) fn schedule(&self, task_id: TaskId, priority: Priority, state: &QueueState) {
+        self.wakeups.fetch_add(1, Ordering::Relaxed);
+        if state.0.compare_exchange(QueueState::IDLE, QueueState::QUEUED, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            self.push(task_id, priority, state);
+        }
+    }
+
+    /// Queues the task again if an overflow lost its wakeup. A task that was
+    /// woken again since, e.g. by an interrupt after the queue was drained, is
+    /// already queued and left alone.
+    pub(crate) fn requeue(&self, task_id: TaskId, priority: Priority, state: &QueueState) {
+        if state.0.compare_exchange(QueueState::LOST, QueueState::QUEUED, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            self.push(task_id, priority, state);
+        }
+    }
+
+    fn push(&self, task_id: TaskId, priority: Priority, state: &QueueState) {
+        if self.queues[priority as usize].push(task_id).is_err() {
+            state.0.store(QueueState::LOST, Ordering::Release);
+            self.overflow.store(true, Ordering::Release);
+        } else {
+            self.high_water.fetch_max(self.len() as u32, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of wakeups so far, including those coalesced into an already queued entry.
+    pub(crate) fn wakeups(&self) -> u32 {
+        self.wakeups.load(Ordering::Relaxed)
+    }
+
+    /// Largest number of tasks queued at once.
+    pub(crate) fn high_water(&self) -> u32 {
+        self.high_water.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn take_overflow(&self) -> bool {
+        self.overflow.swap(false, Ordering::AcqRel)
+    }
+
+    pub(crate) fn pop(&self) -> Option<TaskId> {
+        // an aged level takes precedence, lowest first as it has waited the longest
+        for level in (0..Priority::LEVELS).rev() {
+            if self.skipped[level].load(Ordering::Relaxed) >= AGING_LIMIT {
+                self.skipped[level].store(0, Ordering::Relaxed);
+                if let Some(task_id) = self.queues[level].pop() {
+                    return Some(task_id);
+                }
+            }
+        }
+
+        let level = (0..Priority::LEVELS).find(|&level| !self.queues[level].is_empty())?;
+        let task_id = self.queues[level].pop()?;
+        self.skipped[level].store(0, Ordering::Relaxed);
+        for lower in level + 1..Priority::LEVELS {
+            if !self.queues[lower].is_empty() {
+                self.skipped[lower].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Some(task_id)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queues.iter().all(|queue| queue.is_empty()) && !self.overflow.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+}
@@ -0,0 +1,114 @@
+#![allow(dead_code)]
+
+use core::{cell::{Cell, RefCell}, future::{poll_fn, Future}, pin::Pin, task::Poll};
+
+use alloc::{boxed::Box, vec::Vec};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+
+type Child<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + 'a>>;
+
+/// A nursery of child futures that may borrow from the parent's stack.
+///
+/// Children are not executor tasks: they are polled by `join`, inside the
+/// parent task, so they need not be `'static`. `join` resolves once every
+/// child has finished, or with the first error, dropping the remaining
+/// children. Dropping the scope, e.g. because the parent task was aborted,
+/// drops all of its children with it.
+///
+/// A child that needs the scope itself, to spawn siblings or cancel the
+/// rest, holds it through an `Rc`; `join` borrows nothing while it polls.
+///
+/// ```ignore
+/// let scope = Scope::new();
+/// scope.spawn(async { read_blocks(&mut buf_a).await });
+/// scope.spawn(async { read_blocks(&mut buf_b).await });
+/// scope.join().await?;
+/// ```
+pub(crate) struct Scope<'a, E> {
+    /// Taken out by `join` while it polls the children, so they can use the scope.
+    running: RefCell<Option<FuturesUnordered<Child<'a, E>>>>,
+    pending: RefCell<Vec<Child<'a, E>>>,
+    /// Size of the set `join` has taken out.
+    polling: Cell<usize>,
+    /// Set by `cancel` while `join` has the set out, for `join` to drop it on return.
+    cancelled: Cell<bool>,
+}
+
+impl<'a, E> Scope<'a, E> {
+    pub(crate) fn new() -> Self {
+        Self {
+            running: RefCell::new(Some(FuturesUnordered::new())),
+            pending: RefCell::new(Vec::new()),
+            polling: Cell::new(0),
+            cancelled: Cell::new(false),
+        }
+    }
+
+    /// Adds a child; it starts running at the next poll of `join`.
+    pub(crate) fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = Result<(), E>> + 'a,
+    {
+        self.pending.borrow_mut().push(Box::pin(future));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        let running = match self.running.borrow().as_ref() {
+            Some(running) => running.len(),
+            None if self.cancelled.get() => 0,
+            None => self.polling.get(),
+        };
+        running + self.pending.borrow().len()
+    }
+
+    /// Cancels all children that have not finished yet. Called from a child,
+    /// the children are dropped as soon as that child's poll returns.
+    pub(crate) fn cancel(&self) {
+        self.pending.borrow_mut().clear();
+        match self.running.borrow_mut().as_mut() {
+            Some(running) => running.clear(),
+            None => self.cancelled.set(true),
+        }
+    }
+
+    /// Runs the children to completion, stopping at the first error.
+    pub(crate) async fn join(&self) -> Result<(), E> {
+        poll_fn(|cx| {
+            loop {
+                let mut running = self.running.borrow_mut().take().expect("Scope::join polled by one of its children");
+                running.extend(self.pending.borrow_mut().drain(..));
+
+                self.polling.set(running.len());
+                let res = running.poll_next_unpin(cx);
+                self.polling.set(0);
+                if self.cancelled.replace(false) {
+                    running.clear();
+                }
+                let idle = running.is_empty();
+                *self.running.borrow_mut() = Some(running);
+
+                match res {
+                    Poll::Ready(Some(Ok(()))) => continue,
+                    Poll::Ready(Some(Err(err))) => {
+                        self.cancel();
+                        return Poll::Ready(Err(err));
+                    }
+                    Poll::Ready(None) => {
+                        if self.pending.borrow().is_empty() {
+                            return Poll::Ready(Ok(()));
+                        }
+                    }
+                    // children spawned or cancelled meanwhile need another round
+                    Poll::Pending if idle || !self.pending.borrow().is_empty() => continue,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }).await
+    }
+}
+
+impl<'a, E> Default for Scope<'a, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
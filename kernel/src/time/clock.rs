@@ -0,0 +1,51 @@
+use spin::Mutex;
+
+use super::{get_sys_ticks, timer_queue::{TimerQueue, TIMER_QUEUE}, TICK_HZ};
+
+const fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// A monotonic time source counting ticks at a compile-time rate.
+///
+/// `Instant`, `Duration`, `Timer` and `Ticker` are generic over the clock
+/// they measure; the plain names are aliases for `SysClock`, see `hires` for
+/// the other one. Values of different clocks don't mix.
+pub(crate) trait Clock: Sized + 'static {
+    const HZ: u64;
+    const GCD_1K: u64 = gcd(Self::HZ, 1_000);
+    const GCD_1M: u64 = gcd(Self::HZ, 1_000_000);
+    const GCD_1G: u64 = gcd(Self::HZ, 1_000_000_000);
+
+    /// Ticks since boot.
+    fn now() -> u64;
+
+    /// Where this clock's timers wait for their deadline.
+    fn timer_queue() -> &'static Mutex<TimerQueue<Self>>;
+
+    /// Called with the timer queue locked whenever it changes, with its earliest deadline.
+    fn set_alarm(_deadline: Option<u64>) {}
+}
+
+/// The SysTick clock, at `TICK_HZ`.
+///
+/// The tick interrupt ends every idle sleep, so its timers need no alarm:
+/// the executor checks them each time it wakes up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SysClock;
+
+impl Clock for SysClock {
+    const HZ: u64 = TICK_HZ;
+
+    fn now() -> u64 {
+        get_sys_ticks()
+    }
+
+    fn timer_queue() -> &'static Mutex<TimerQueue<Self>> {
+        &TIMER_QUEUE
+    }
+}
@@ -1,176 +1,230 @@
-use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+use core::{cmp::Ordering, marker::PhantomData, ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign}, str::FromStr};
 
-use super::{GCD_1G, GCD_1K, GCD_1M, TICK_HZ};
+use super::{clock::{Clock, SysClock}, format::{self, ParseDurationError}};
 
 #[inline]
 const fn div_ceil(num: u64, den: u64) -> u64 {
     (num + den - 1) / den
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Duration {
-    pub(super) ticks: u64,
+/// A span of system clock ticks.
+pub(crate) type Duration = DurationOf<SysClock>;
+
+pub(crate) struct DurationOf<C: Clock> {
+    ticks: u64,
+    clock: PhantomData<C>,
 }
 
-impl Duration {
-    pub(crate) const MIN: Duration = Duration { ticks: u64::MIN };
-    pub(crate) const MAX: Duration = Duration { ticks: u64::MAX };
+impl<C: Clock> DurationOf<C> {
+    pub(crate) const MIN: Self = Self::from_ticks(u64::MIN);
+    pub(crate) const MAX: Self = Self::from_ticks(u64::MAX);
 
     pub(crate) const fn as_ticks(&self) -> u64 {
         self.ticks
     }
 
     pub(crate) const fn as_secs(&self) -> u64 {
-        self.ticks / TICK_HZ
+        self.ticks / C::HZ
     }
 
     pub(crate) const fn as_millis(&self) -> u64 {
-        self.ticks * (1_000 / GCD_1K) / (TICK_HZ / GCD_1K)
+        self.ticks * (1_000 / C::GCD_1K) / (C::HZ / C::GCD_1K)
     }
 
     pub(crate) const fn as_micros(&self) -> u64 {
-        self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
+        self.ticks * (1_000_000 / C::GCD_1M) / (C::HZ / C::GCD_1M)
+    }
+
+    pub(crate) const fn as_nanos(&self) -> u128 {
+        self.ticks as u128 * 1_000_000_000 / C::HZ as u128
     }
 
     pub(crate) const fn from_ticks(ticks: u64) -> Self {
-        Self { ticks }
+        Self { ticks, clock: PhantomData }
     }
 
     pub(crate) const fn from_secs(secs: u64) -> Self {
-        Self { ticks: secs * TICK_HZ }
+        Self::from_ticks(secs * C::HZ)
     }
 
     pub(crate) const fn from_millis(millis: u64) -> Self {
-        Self {
-            ticks: div_ceil(millis * (TICK_HZ / GCD_1K), 1000 / GCD_1K),
-        }
+        Self::from_ticks(div_ceil(millis * (C::HZ / C::GCD_1K), 1000 / C::GCD_1K))
     }
 
     pub(crate) const fn from_micros(micros: u64) -> Self {
-        Self {
-            ticks: div_ceil(micros * (TICK_HZ / GCD_1M), 1_000_000 / GCD_1M),
-        }
+        Self::from_ticks(div_ceil(micros * (C::HZ / C::GCD_1M), 1_000_000 / C::GCD_1M))
     }
 
     pub(crate) const fn from_nanos(nanos: u64) -> Self {
-        Self {
-            ticks: div_ceil(nanos * (TICK_HZ / GCD_1G), 1_000_000_000 / GCD_1G),
-        }
+        Self::from_ticks(div_ceil(nanos * (C::HZ / C::GCD_1G), 1_000_000_000 / C::GCD_1G))
     }
 
     pub(crate) const fn from_secs_floor(secs: u64) -> Self {
-        Self { ticks: secs * TICK_HZ }
+        Self::from_ticks(secs * C::HZ)
     }
 
     pub(crate) const fn from_millis_floor(millis: u64) -> Self {
-        Self {
-            ticks: millis * (TICK_HZ / GCD_1K) / (1_000 / GCD_1K),
-        }
+        Self::from_ticks(millis * (C::HZ / C::GCD_1K) / (1_000 / C::GCD_1K))
     }
 
     pub(crate) const fn from_micros_floor(micros: u64) -> Self {
-        Self {
-            ticks: micros * (TICK_HZ / GCD_1M) / (1_000_000 / GCD_1M),
-        }
+        Self::from_ticks(micros * (C::HZ / C::GCD_1M) / (1_000_000 / C::GCD_1M))
     }
 
     pub(crate) const fn from_hz(hz: u64) -> Self {
-        let ticks = if hz >= TICK_HZ {
+        let ticks = if hz >= C::HZ {
             1
         } else {
-            (TICK_HZ + hz / 2) / hz
+            (C::HZ + hz / 2) / hz
         };
-        Self { ticks }
+        Self::from_ticks(ticks)
+    }
+
+    pub(crate) fn checked_add(&self, rhs: Self) -> Option<Self> {
+        self.ticks.checked_add(rhs.ticks).map(Self::from_ticks)
+    }
+
+    pub(crate) fn checked_sub(&self, rhs: Self) -> Option<Self> {
+        self.ticks.checked_sub(rhs.ticks).map(Self::from_ticks)
+    }
+
+    pub(crate) fn checked_mul(&self, rhs: u64) -> Option<Self> {
+        self.ticks.checked_mul(rhs).map(Self::from_ticks)
+    }
+
+    pub(crate) fn checked_div(&self, rhs: u64) -> Option<Self> {
+        self.ticks.checked_div(rhs).map(Self::from_ticks)
+    }
+
+    /// The same span on clock `D`, rounded up to its tick.
+    pub(crate) fn to_clock<D: Clock>(self) -> DurationOf<D> {
+        DurationOf::from_ticks((self.ticks as u128 * D::HZ as u128).div_ceil(C::HZ as u128) as u64)
+    }
+}
+
+impl<C: Clock> Clone for DurationOf<C> {
+    fn clone(&self) -> Self {
+        *self
     }
+}
 
-    pub(crate) fn checked_add(&self, rhs: Duration) -> Option<Duration> {
-        self.ticks.checked_add(rhs.ticks).map(|ticks| Duration { ticks })
+impl<C: Clock> Copy for DurationOf<C> {}
+
+impl<C: Clock> Default for DurationOf<C> {
+    fn default() -> Self {
+        Self::from_ticks(0)
     }
+}
 
-    pub(crate) fn checked_sub(&self, rhs: Duration) -> Option<Duration> {
-        self.ticks.checked_sub(rhs.ticks).map(|ticks| Duration { ticks })
+impl<C: Clock> PartialEq for DurationOf<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ticks == other.ticks
     }
+}
+
+impl<C: Clock> Eq for DurationOf<C> {}
 
-    pub(crate) fn checked_mul(&self, rhs: u64) -> Option<Duration> {
-        self.ticks.checked_mul(rhs).map(|ticks| Duration { ticks })
+impl<C: Clock> PartialOrd for DurationOf<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    pub(crate) fn checked_div(&self, rhs: u64) -> Option<Duration> {
-        self.ticks.checked_div(rhs).map(|ticks| Duration { ticks })
+impl<C: Clock> Ord for DurationOf<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ticks.cmp(&other.ticks)
     }
 }
 
-impl Add for Duration {
-    type Output = Duration;
+impl<C: Clock> core::fmt::Debug for DurationOf<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Duration").field("ticks", &self.ticks).finish()
+    }
+}
+
+impl<C: Clock> Add for DurationOf<C> {
+    type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         self.checked_add(rhs).expect("overflow when adding durations")
     }
 }
 
-impl AddAssign for Duration {
+impl<C: Clock> AddAssign for DurationOf<C> {
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
     }
 }
 
-impl Sub for Duration {
-    type Output = Duration;
+impl<C: Clock> Sub for DurationOf<C> {
+    type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
         self.checked_sub(rhs).expect("overflow when subtracting durations")
     }
 }
 
-impl SubAssign for Duration {
+impl<C: Clock> SubAssign for DurationOf<C> {
     fn sub_assign(&mut self, rhs: Self) {
         *self = *self - rhs;
     }
 }
 
-impl Mul<u64> for Duration {
-    type Output = Duration;
+impl<C: Clock> Mul<u64> for DurationOf<C> {
+    type Output = Self;
 
     fn mul(self, rhs: u64) -> Self::Output {
         self.checked_mul(rhs).expect("overflow when multiplying duration by scale")
     }
 }
 
-impl Mul<Duration> for u64 {
-    type Output = Duration;
+impl<C: Clock> Mul<DurationOf<C>> for u64 {
+    type Output = DurationOf<C>;
 
-    fn mul(self, rhs: Duration) -> Self::Output {
+    fn mul(self, rhs: DurationOf<C>) -> Self::Output {
         rhs * self
     }
 }
 
-impl MulAssign<u64> for Duration {
+impl<C: Clock> MulAssign<u64> for DurationOf<C> {
     fn mul_assign(&mut self, rhs: u64) {
         *self = *self * rhs;
     }
 }
 
-impl Div<u64> for Duration {
-    type Output = Duration;
+impl<C: Clock> Div<u64> for DurationOf<C> {
+    type Output = Self;
 
     fn div(self, rhs: u64) -> Self::Output {
         self.checked_div(rhs).expect("divide by zero error when dividing duration by scalar")
     }
 }
 
-impl DivAssign<u64> for Duration {
+impl<C: Clock> DivAssign<u64> for DurationOf<C> {
     fn div_assign(&mut self, rhs: u64) {
         *self = *self / rhs;
     }
 }
 
-impl core::fmt::Display for Duration {
+/// Formats as the largest fitting units, e.g. `1h30m`, `1.5s` or `250ms`.
+impl<C: Clock> core::fmt::Display for DurationOf<C> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "Duration: {} ticks", self.ticks)
+        format::fmt_duration(self.as_nanos(), f)
+    }
+}
+
+/// Parses unit-suffixed parts such as `1h30m`, `250ms` or `1.5s`, rounding up to a tick.
+impl<C: Clock> FromStr for DurationOf<C> {
+    type Err = ParseDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let nanos = format::parse_nanos(s)?;
+        let ticks = nanos.checked_mul(C::HZ as u128).ok_or(ParseDurationError::Overflow)?.div_ceil(1_000_000_000);
+        u64::try_from(ticks).map(Self::from_ticks).map_err(|_| ParseDurationError::Overflow)
     }
 }
 
-impl TryFrom<core::time::Duration> for Duration {
+impl<C: Clock> TryFrom<core::time::Duration> for DurationOf<C> {
     type Error = <u64 as TryFrom<u128>>::Error;
 
     fn try_from(value: core::time::Duration) -> Result<Self, Self::Error> {
@@ -178,8 +232,8 @@ impl TryFrom<core::time::Duration> for Duration {
     }
 }
 
-impl From<Duration> for core::time::Duration {
-    fn from(value: Duration) -> Self {
+impl<C: Clock> From<DurationOf<C>> for core::time::Duration {
+    fn from(value: DurationOf<C>) -> Self {
         core::time::Duration::from_micros(value.as_micros())
     }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+//! Human-readable durations, shared by `Duration` and `Instant` on any clock.
+//!
+//! Durations read and print as unit-suffixed parts such as `1h30m`, `250ms`
+//! or `1.5s`; instants print as uptime, `3d 04:12:55.123`.
+
+use core::fmt::{self, Write};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+const SECS_PER_DAY: u128 = 24 * 60 * 60;
+
+/// Suffixes and their length in nanoseconds, longest suffix first where they share a prefix.
+const UNITS: [(&str, u128); 8] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", NANOS_PER_SEC),
+    ("m", 60 * NANOS_PER_SEC),
+    ("h", 60 * 60 * NANOS_PER_SEC),
+    ("d", SECS_PER_DAY * NANOS_PER_SEC),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParseDurationError {
+    Empty,
+    /// A part does not start with a number, e.g. `s` or `.s`.
+    InvalidNumber,
+    /// A number has no unit after it, e.g. `10`.
+    MissingUnit,
+    UnknownUnit,
+    /// The span does not fit in the clock's tick counter.
+    Overflow,
+}
+
+impl fmt::Display for ParseDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseDurationError::Empty => "empty duration",
+            ParseDurationError::InvalidNumber => "invalid number in duration",
+            ParseDurationError::MissingUnit => "missing unit in duration, e.g. 10s",
+            ParseDurationError::UnknownUnit => "unknown unit in duration, use d, h, m, s, ms, us or ns",
+            ParseDurationError::Overflow => "duration too long",
+        })
+    }
+}
+
+/// Parses a sequence of `<number>[.<fraction>]<unit>` parts, such as `1h30m`
+/// or `1.5s`, into nanoseconds. A plain `0` needs no unit.
+pub(crate) fn parse_nanos(s: &str) -> Result<u128, ParseDurationError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(ParseDurationError::Empty);
+    }
+    if s == "0" {
+        return Ok(0);
+    }
+
+    let mut rest = s;
+    let mut total = 0u128;
+    while !rest.is_empty() {
+        let int_len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (int, tail) = rest.split_at(int_len);
+        let (frac, tail) = match tail.strip_prefix('.') {
+            Some(tail) => tail.split_at(tail.find(|c: char| !c.is_ascii_digit()).unwrap_or(tail.len())),
+            None => ("", tail),
+        };
+        if int.is_empty() && frac.is_empty() {
+            return Err(ParseDurationError::InvalidNumber);
+        }
+
+        let unit_len = tail.find(|c: char| c.is_ascii_digit() || c == '.' || c.is_whitespace()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        if unit.is_empty() {
+            return Err(ParseDurationError::MissingUnit);
+        }
+        let scale = UNITS.iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, scale)| *scale)
+            .ok_or(ParseDurationError::UnknownUnit)?;
+
+        let mut nanos = parse_digits(int)?.checked_mul(scale).ok_or(ParseDurationError::Overflow)?;
+        // digits past a nanosecond carry no weight, and would overflow the divisor
+        let frac = &frac[..frac.len().min(18)];
+        if !frac.is_empty() {
+            nanos += parse_digits(frac)? * scale / 10u128.pow(frac.len() as u32);
+        }
+        total = total.checked_add(nanos).ok_or(ParseDurationError::Overflow)?;
+        rest = tail.trim_start();
+    }
+    Ok(total)
+}
+
+fn parse_digits(digits: &str) -> Result<u128, ParseDurationError> {
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse().map_err(|_| ParseDurationError::Overflow)
+}
+
+/// Writes `nanos` as the largest fitting units, e.g. `1h30m`, `1.5s` or `250us`.
+pub(crate) fn fmt_duration(nanos: u128, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut buf = Buf::new();
+    if nanos == 0 {
+        buf.write_str("0s")?;
+    } else if nanos < 1_000 {
+        write!(buf, "{}ns", nanos)?;
+    } else if nanos < 1_000_000 {
+        write_scaled(&mut buf, nanos, 1_000, "us")?;
+    } else if nanos < NANOS_PER_SEC {
+        write_scaled(&mut buf, nanos, 1_000_000, "ms")?;
+    } else {
+        let secs = nanos / NANOS_PER_SEC;
+        for (value, unit) in [(secs / SECS_PER_DAY, "d"), (secs / 3_600 % 24, "h"), (secs / 60 % 60, "m")] {
+            if value > 0 {
+                write!(buf, "{}{}", value, unit)?;
+            }
+        }
+        let rest = nanos % (60 * NANOS_PER_SEC);
+        if rest > 0 {
+            write_scaled(&mut buf, rest, NANOS_PER_SEC, "s")?;
+        }
+    }
+    f.pad(buf.as_str())
+}
+
+/// Writes `nanos` as uptime, `[<days>d ]HH:MM:SS.mmm`.
+pub(crate) fn fmt_uptime(nanos: u128, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut buf = Buf::new();
+    let secs = nanos / NANOS_PER_SEC;
+    let days = secs / SECS_PER_DAY;
+    if days > 0 {
+        write!(buf, "{}d ", days)?;
+    }
+    write!(
+        buf,
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3_600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        nanos % NANOS_PER_SEC / 1_000_000,
+    )?;
+    f.pad(buf.as_str())
+}
+
+/// Writes `value / scale` with as many decimals as it needs, then `unit`.
+fn write_scaled(buf: &mut Buf, value: u128, scale: u128, unit: &str) -> fmt::Result {
+    write!(buf, "{}", value / scale)?;
+    let mut frac = value % scale;
+    if frac > 0 {
+        let mut digits = scale.ilog10() as usize;
+        while frac.is_multiple_of(10) {
+            frac /= 10;
+            digits -= 1;
+        }
+        write!(buf, ".{:0width$}", frac, width = digits)?;
+    }
+    buf.write_str(unit)
+}
+
+/// Enough room for the longest duration, `213503982334d23h59m59.999999999s`.
+struct Buf {
+    bytes: [u8; 48],
+    len: usize,
+}
+
+impl Buf {
+    fn new() -> Self {
+        Self { bytes: [0; 48], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        // only ever filled from `&str`s
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
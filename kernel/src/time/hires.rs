@@ -0,0 +1,120 @@
+//! High-resolution clock for short driver delays, next to the SysTick one.
+//!
+//! The board runs a free-running 32-bit counter at `HIRES_HZ` (TIM2) with a
+//! compare channel as the alarm. The counter is extended to 64 bits by
+//! counting half periods: the board calls `hires_period_handler` when the
+//! counter wraps and when it crosses its midpoint, which lets `now` tell a
+//! fresh wrap from a stale period count without masking interrupts.
+//!
+//! ```c
+//! extern void hires_period_handler(void);
+//! extern void hires_alarm_handler(void);
+//! ```
+
+use core::{sync::atomic::{compiler_fence, AtomicBool, AtomicU32, Ordering}, task::Waker};
+
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use crate::c_api::{cancel_hires_alarm, get_hires_ticks, set_hires_alarm};
+use super::{clock::Clock, duration::DurationOf, instant::InstantOf, timer::{TickerOf, TimerOf}, timer_queue::TimerQueue, AtomicTicks};
+
+pub(crate) const HIRES_HZ: u64 = 1_000_000;
+
+pub(crate) type HiResInstant = InstantOf<HiResClock>;
+pub(crate) type HiResDuration = DurationOf<HiResClock>;
+pub(crate) type HiResTimer = TimerOf<HiResClock>;
+pub(crate) type HiResTicker = TickerOf<HiResClock>;
+
+static HIRES_TIMER_QUEUE: Mutex<TimerQueue<HiResClock>> = Mutex::new(TimerQueue::new());
+
+/// Half periods of the hardware counter since boot.
+static PERIOD: AtomicU32 = AtomicU32::new(0);
+/// Ticks the virtual clock skipped ahead of the hardware counter.
+static SKIPPED: AtomicTicks = AtomicTicks::new();
+/// Set when the alarm (or a half period) fired and the timer queue must be checked.
+static ALARM_FIRED: AtomicBool = AtomicBool::new(false);
+
+/// The TIM2 clock, at `HIRES_HZ`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct HiResClock;
+
+impl Clock for HiResClock {
+    const HZ: u64 = HIRES_HZ;
+
+    fn now() -> u64 {
+        hardware_now() + SKIPPED.load()
+    }
+
+    fn timer_queue() -> &'static Mutex<TimerQueue<Self>> {
+        &HIRES_TIMER_QUEUE
+    }
+
+    fn set_alarm(deadline: Option<u64>) {
+        let Some(deadline) = deadline else {
+            unsafe { cancel_hires_alarm() };
+            return;
+        };
+
+        let now = Self::now();
+        if deadline <= now {
+            ALARM_FIRED.store(true, Ordering::Release);
+        } else if deadline - now >= 1 << 31 {
+            // out of the compare register's reach; a later half period re-arms it
+            unsafe { cancel_hires_alarm() };
+        } else {
+            unsafe { set_hires_alarm((deadline - SKIPPED.load()) as u32) };
+            // the counter may have passed the compare value while it was being set
+            if Self::now() >= deadline {
+                ALARM_FIRED.store(true, Ordering::Release);
+            }
+        }
+    }
+}
+
+fn hardware_now() -> u64 {
+    let period = PERIOD.load(Ordering::Acquire);
+    compiler_fence(Ordering::Acquire);
+    let counter = unsafe { get_hires_ticks() };
+    // an odd period means the midpoint was seen; a counter back in its lower
+    // half then belongs to the next wrap, whose handler has not run yet
+    ((period as u64) << 31) + (counter ^ ((period & 1) << 31)) as u64
+}
+
+#[no_mangle]
+pub extern "C" fn hires_period_handler() {
+    PERIOD.fetch_add(1, Ordering::AcqRel);
+    ALARM_FIRED.store(true, Ordering::Release);
+}
+
+#[no_mangle]
+pub extern "C" fn hires_alarm_handler() {
+    ALARM_FIRED.store(true, Ordering::Release);
+}
+
+pub(crate) fn has_pending() -> bool {
+    ALARM_FIRED.load(Ordering::Acquire)
+}
+
+/// Removes the expired high-resolution timers and re-arms the alarm, if it has fired.
+pub(crate) fn take_expired() -> Vec<Waker> {
+    if ALARM_FIRED.swap(false, Ordering::AcqRel) {
+        HIRES_TIMER_QUEUE.lock().take_expired(HiResInstant::now())
+    } else {
+        Vec::new()
+    }
+}
+
+/// The earliest high-resolution timer deadline, if any.
+pub(crate) fn next_deadline() -> Option<HiResInstant> {
+    HIRES_TIMER_QUEUE.lock().next_deadline()
+}
+
+/// Moves the clock forward to `ticks`; only the virtual clock should do this.
+pub(crate) fn advance_hires_ticks_to(ticks: u64) {
+    let now = HiResClock::now();
+    if ticks > now {
+        SKIPPED.store(SKIPPED.load() + (ticks - now));
+        ALARM_FIRED.store(true, Ordering::Release);
+    }
+}
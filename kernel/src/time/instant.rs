@@ -1,41 +1,37 @@
-use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::{cmp::Ordering, marker::PhantomData, ops::{Add, AddAssign, Sub, SubAssign}};
 
-use super::{duration::Duration, get_sys_ticks, GCD_1K, GCD_1M, TICK_HZ};
+use super::{clock::{Clock, SysClock}, duration::DurationOf, format};
 
+/// An instant of the system clock.
+pub(crate) type Instant = InstantOf<SysClock>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Instant {
+pub(crate) struct InstantOf<C: Clock> {
     ticks: u64,
+    clock: PhantomData<C>,
 }
 
-impl Instant {
-    pub(crate) const MIN: Instant = Instant { ticks: u64::MIN };
-    pub(crate) const MAX: Instant = Instant { ticks: u64::MAX };
+impl<C: Clock> InstantOf<C> {
+    pub(crate) const MIN: Self = Self::from_ticks(u64::MIN);
+    pub(crate) const MAX: Self = Self::from_ticks(u64::MAX);
 
     pub(crate) fn now() -> Self {
-        Self { ticks: get_sys_ticks() }
+        Self::from_ticks(C::now())
     }
 
     pub(crate) const fn from_ticks(ticks: u64) -> Self {
-        Self { ticks }
+        Self { ticks, clock: PhantomData }
     }
 
     pub(crate) const fn from_micros(micros: u64) -> Self {
-        Self {
-            ticks: micros * (TICK_HZ / GCD_1M) / (1_000_000 / GCD_1M),
-        }
+        Self::from_ticks(micros * (C::HZ / C::GCD_1M) / (1_000_000 / C::GCD_1M))
     }
 
     pub(crate) const fn from_millis(millis: u64) -> Self {
-        Self {
-            ticks: millis * (TICK_HZ / GCD_1K) / (1_000 / GCD_1K),
-        }
+        Self::from_ticks(millis * (C::HZ / C::GCD_1K) / (1_000 / C::GCD_1K))
     }
 
     pub(crate) const fn from_secs(secs: u64) -> Self {
-        Self {
-            ticks: secs * TICK_HZ,
-        }
+        Self::from_ticks(secs * C::HZ)
     }
 
     pub(crate) const fn as_ticks(&self) -> u64 {
@@ -43,92 +39,118 @@ impl Instant {
     }
 
     pub(crate) const fn as_secs(&self) -> u64 {
-        self.ticks / TICK_HZ
+        self.ticks / C::HZ
     }
 
     pub(crate) const fn as_millis(&self) -> u64 {
-        self.ticks * (1_000 / GCD_1K) / (TICK_HZ / GCD_1K)
+        self.ticks * (1_000 / C::GCD_1K) / (C::HZ / C::GCD_1K)
     }
 
     pub(crate) const fn as_micros(&self) -> u64 {
-        self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
+        self.ticks * (1_000_000 / C::GCD_1M) / (C::HZ / C::GCD_1M)
     }
 
-    pub(crate) fn duration_since(&self, earlier: Instant) -> Duration {
-        Duration {
-            ticks: self.ticks.checked_sub(earlier.ticks).expect("since error"),
-        }
+    pub(crate) fn duration_since(&self, earlier: Self) -> DurationOf<C> {
+        DurationOf::from_ticks(self.ticks.checked_sub(earlier.ticks).expect("since error"))
     }
 
-    pub(crate) fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
-        if self.ticks < earlier.ticks {
-            None
-        } else {
-            Some(Duration {
-                ticks: self.ticks - earlier.ticks,
-            })
-        }
+    pub(crate) fn checked_duration_since(&self, earlier: Self) -> Option<DurationOf<C>> {
+        self.ticks.checked_sub(earlier.ticks).map(DurationOf::from_ticks)
     }
 
-    pub(crate) fn saturating_duration_since(&self, earlier: Instant) -> Duration {
-        Duration {
-            ticks: if self.ticks < earlier.ticks {
-                0
-            } else {
-                self.ticks - earlier.ticks
-            },
-        }
+    pub(crate) fn saturating_duration_since(&self, earlier: Self) -> DurationOf<C> {
+        DurationOf::from_ticks(self.ticks.saturating_sub(earlier.ticks))
     }
 
-    pub(crate) fn elapsed(&self) -> Duration {
-        Instant::now() - *self
+    pub(crate) fn elapsed(&self) -> DurationOf<C> {
+        Self::now() - *self
     }
 
-    pub(crate) fn checked_add(&self, dura: Duration) -> Option<Instant> {
-        self.ticks.checked_add(dura.ticks).map(|ticks| Instant {ticks})
+    pub(crate) fn checked_add(&self, dura: DurationOf<C>) -> Option<Self> {
+        self.ticks.checked_add(dura.as_ticks()).map(Self::from_ticks)
     }
 
-    pub(crate) fn checked_sub(&self, dura: Duration) -> Option<Instant> {
-        self.ticks.checked_sub(dura.ticks).map(|ticks| Instant {ticks})
+    pub(crate) fn checked_sub(&self, dura: DurationOf<C>) -> Option<Self> {
+        self.ticks.checked_sub(dura.as_ticks()).map(Self::from_ticks)
+    }
+
+    /// The same moment on clock `D`, rounded down to its tick; both clocks count from boot.
+    pub(crate) fn to_clock<D: Clock>(self) -> InstantOf<D> {
+        InstantOf::from_ticks((self.ticks as u128 * D::HZ as u128 / C::HZ as u128) as u64)
+    }
+}
+
+impl<C: Clock> Clone for InstantOf<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: Clock> Copy for InstantOf<C> {}
+
+impl<C: Clock> PartialEq for InstantOf<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ticks == other.ticks
+    }
+}
+
+impl<C: Clock> Eq for InstantOf<C> {}
+
+impl<C: Clock> PartialOrd for InstantOf<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Clock> Ord for InstantOf<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ticks.cmp(&other.ticks)
+    }
+}
+
+impl<C: Clock> core::fmt::Debug for InstantOf<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Instant").field("ticks", &self.ticks).finish()
     }
 }
 
-impl Add<Duration> for Instant {
-    type Output = Instant;
-    fn add(self, rhs: Duration) -> Self::Output {
+impl<C: Clock> Add<DurationOf<C>> for InstantOf<C> {
+    type Output = Self;
+    fn add(self, rhs: DurationOf<C>) -> Self::Output {
         self.checked_add(rhs).expect("Instant adding Duration is overflowed")
     }
 }
 
-impl AddAssign<Duration> for Instant {
-    fn add_assign(&mut self, rhs: Duration) {
+impl<C: Clock> AddAssign<DurationOf<C>> for InstantOf<C> {
+    fn add_assign(&mut self, rhs: DurationOf<C>) {
         *self = *self + rhs;
     }
 }
 
-impl Sub<Duration> for Instant {
-    type Output = Instant;
-    fn sub(self, rhs: Duration) -> Self::Output {
+impl<C: Clock> Sub<DurationOf<C>> for InstantOf<C> {
+    type Output = Self;
+    fn sub(self, rhs: DurationOf<C>) -> Self::Output {
         self.checked_sub(rhs).expect("Instant subbing Duration is overflowed")
     }
 }
 
-impl SubAssign<Duration> for Instant {
-    fn sub_assign(&mut self, rhs: Duration) {
+impl<C: Clock> SubAssign<DurationOf<C>> for InstantOf<C> {
+    fn sub_assign(&mut self, rhs: DurationOf<C>) {
         *self = *self - rhs;
     }
 }
 
-impl Sub<Instant> for Instant {
-    type Output = Duration;
+impl<C: Clock> Sub for InstantOf<C> {
+    type Output = DurationOf<C>;
 
-    fn sub(self, rhs: Instant) -> Self::Output {
+    fn sub(self, rhs: Self) -> Self::Output {
         self.checked_duration_since(rhs).unwrap()
     }
 }
 
-impl core::fmt::Display for Instant {
+/// Formats as time since boot, e.g. `3d 04:12:55.123`.
+impl<C: Clock> core::fmt::Display for InstantOf<C> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{} ticks", self.ticks)
+        format::fmt_uptime(self.ticks as u128 * 1_000_000_000 / C::HZ as u128, f)
     }
 }
\ No newline at end of file
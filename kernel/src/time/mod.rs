@@ -1,32 +1,87 @@
 #![allow(dead_code)]
 
-use core::sync::atomic::AtomicU32;
+use core::sync::atomic::{AtomicU32, Ordering};
 
+use crate::c_api::{irq_restore, irq_save};
+
+pub(crate) mod clock;
 pub(crate) mod instant;
 pub(crate) mod duration;
+pub(crate) mod format;
+pub(crate) mod hires;
+pub(crate) mod rtc;
+pub(crate) mod system_time;
 pub(crate) mod timer;
+pub(crate) mod timer_queue;
+pub(crate) mod virtual_clock;
 
 pub(crate) const TICK_HZ: u64 = 1_000;
-const GCD_1K: u64 = gcd(TICK_HZ, 1_000);
-const GCD_1M: u64 = gcd(TICK_HZ, 1_000_000);
-const GCD_1G: u64 = gcd(TICK_HZ, 1_000_000_000);
-
-const fn gcd(a: u64, b: u64) -> u64 {
-    if b == 0 {
-        a
-    } else {
-        gcd(b, a % b)
+
+/// A 64-bit tick count kept as two halves, since Cortex-M7 has no 64-bit atomics.
+///
+/// Writers bump `seq` to odd before touching the halves and back to even
+/// afterwards; readers retry until they see the same even sequence on both
+/// sides of their reads, so a write landing mid-read (or the low half
+/// wrapping into the high one) never yields a torn value. Only one writer
+/// may run at a time, and it writes with interrupts masked: a handler that
+/// reads the ticks (e.g. by waking a task) while preempting a half-done
+/// write would otherwise spin on the odd sequence forever.
+struct AtomicTicks {
+    lo: AtomicU32,
+    hi: AtomicU32,
+    seq: AtomicU32,
+}
+
+impl AtomicTicks {
+    const fn new() -> Self {
+        Self {
+            lo: AtomicU32::new(0),
+            hi: AtomicU32::new(0),
+            seq: AtomicU32::new(0),
+        }
+    }
+
+    fn load(&self) -> u64 {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            let hi = self.hi.load(Ordering::Acquire);
+            let lo = self.lo.load(Ordering::Acquire);
+            if self.seq.load(Ordering::Acquire) == seq {
+                return ((hi as u64) << 32) | lo as u64;
+            }
+        }
+    }
+
+    fn store(&self, ticks: u64) {
+        let primask = unsafe { irq_save() };
+        self.seq.fetch_add(1, Ordering::AcqRel);
+        self.hi.store((ticks >> 32) as u32, Ordering::Release);
+        self.lo.store(ticks as u32, Ordering::Release);
+        self.seq.fetch_add(1, Ordering::AcqRel);
+        unsafe { irq_restore(primask) };
     }
 }
 
-pub(crate) static  SYS_TICKS: AtomicU32 = AtomicU32::new(0);
+/// Uptime in system ticks, written by the SysTick handler, or by the virtual clock instead of it.
+static SYS_TICKS: AtomicTicks = AtomicTicks::new();
 
 
 #[no_mangle]
 pub extern "C" fn sys_tick_handler() {
-    SYS_TICKS.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+    SYS_TICKS.store(SYS_TICKS.load() + 1);
 }
 
 pub(crate) fn get_sys_ticks() -> u64 {
-    SYS_TICKS.load(core::sync::atomic::Ordering::Acquire) as u64
-}
\ No newline at end of file
+    SYS_TICKS.load()
+}
+
+/// Moves the tick counter forward to `ticks`; only the virtual clock should do this.
+pub(crate) fn advance_sys_ticks_to(ticks: u64) {
+    if ticks > get_sys_ticks() {
+        SYS_TICKS.store(ticks);
+    }
+}
@@ -0,0 +1,131 @@
+//! Wall-clock time, kept by an RTC backend.
+//!
+//! `init` picks the board's RTC if it runs, and otherwise a software clock
+//! that only counts uptime from the last `set`. Either way the time reads as
+//! unset until someone sets it, e.g. with the `date` command.
+
+use alloc::boxed::Box;
+use conquer_once::spin::OnceCell;
+use spin::Mutex;
+
+use crate::{c_api::{rtc_get, rtc_init, rtc_set, RtcTime}, info, log, println, warn};
+use super::{instant::Instant, system_time::{CalendarTime, SystemTime}};
+
+static RTC: OnceCell<Box<dyn RtcBackend>> = OnceCell::uninit();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RtcError {
+    /// The backend cannot represent the time, e.g. a year past the RTC's century.
+    OutOfRange,
+    Hardware,
+}
+
+/// A source of wall-clock time.
+pub(crate) trait RtcBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The current time, or `None` if the clock was never set.
+    fn now(&self) -> Option<SystemTime>;
+
+    fn set(&self, time: SystemTime) -> Result<(), RtcError>;
+}
+
+/// The STM32 RTC, counting calendar time on the LSE crystal and battery backed.
+///
+/// Its calendar only holds years 2000 to 2099.
+pub(crate) struct Stm32Rtc;
+
+impl RtcBackend for Stm32Rtc {
+    fn name(&self) -> &'static str {
+        "stm32 rtc"
+    }
+
+    fn now(&self) -> Option<SystemTime> {
+        let mut raw = RtcTime::default();
+        if unsafe { rtc_get(&mut raw) } != 0 {
+            return None;
+        }
+        CalendarTime::new(raw.year, raw.month, raw.day, raw.hour, raw.minute, raw.second, raw.millis)
+            .map(SystemTime::from)
+    }
+
+    fn set(&self, time: SystemTime) -> Result<(), RtcError> {
+        let time = time.to_calendar();
+        if !(2000..=2099).contains(&time.year) {
+            return Err(RtcError::OutOfRange);
+        }
+        let raw = RtcTime {
+            year: time.year,
+            month: time.month,
+            day: time.day,
+            hour: time.hour,
+            minute: time.minute,
+            second: time.second,
+            weekday: time.weekday(),
+            millis: time.millis,
+        };
+        match unsafe { rtc_set(&raw) } {
+            0 => Ok(()),
+            _ => Err(RtcError::Hardware),
+        }
+    }
+}
+
+/// Wall-clock time as uptime added to the last time set; lost on reset.
+pub(crate) struct SoftRtc {
+    epoch: Mutex<Option<(SystemTime, Instant)>>,
+}
+
+impl SoftRtc {
+    pub(crate) const fn new() -> Self {
+        Self { epoch: Mutex::new(None) }
+    }
+}
+
+impl RtcBackend for SoftRtc {
+    fn name(&self) -> &'static str {
+        "soft rtc"
+    }
+
+    fn now(&self) -> Option<SystemTime> {
+        let (time, at) = (*self.epoch.lock())?;
+        time.checked_add(at.elapsed())
+    }
+
+    fn set(&self, time: SystemTime) -> Result<(), RtcError> {
+        *self.epoch.lock() = Some((time, Instant::now()));
+        Ok(())
+    }
+}
+
+/// Selects the RTC backend; later calls keep the first choice.
+pub(crate) fn init() {
+    RTC.init_once(|| -> Box<dyn RtcBackend> {
+        if unsafe { rtc_init() } == 0 {
+            Box::new(Stm32Rtc)
+        } else {
+            warn!("rtc not running, wall clock falls back to uptime");
+            Box::new(SoftRtc::new())
+        }
+    });
+    if let Some(time) = now() {
+        info!("wall clock: {}", time);
+    }
+}
+
+fn backend() -> &'static dyn RtcBackend {
+    RTC.get_or_init(|| Box::new(SoftRtc::new())).as_ref()
+}
+
+pub(crate) fn backend_name() -> &'static str {
+    backend().name()
+}
+
+/// The current wall-clock time, or `None` if it was never set.
+pub(crate) fn now() -> Option<SystemTime> {
+    backend().now()
+}
+
+pub(crate) fn set(time: SystemTime) -> Result<(), RtcError> {
+    backend().set(time)
+}
@@ -0,0 +1,208 @@
+use core::{fmt, ops::Add, str::FromStr};
+
+use super::duration::Duration;
+
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1_000;
+const MIN_YEAR: u16 = 1970;
+const MAX_YEAR: u16 = 9999;
+
+/// Wall-clock time, in milliseconds since 1970-01-01 00:00:00 UTC.
+///
+/// Unlike `Instant` it can jump when the clock is set, so measure intervals with `Instant`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SystemTime {
+    millis: u64,
+}
+
+impl SystemTime {
+    pub(crate) const UNIX_EPOCH: SystemTime = SystemTime { millis: 0 };
+
+    pub(crate) const fn from_unix_secs(secs: u64) -> Self {
+        Self { millis: secs * 1_000 }
+    }
+
+    pub(crate) const fn from_unix_millis(millis: u64) -> Self {
+        Self { millis }
+    }
+
+    pub(crate) const fn as_unix_secs(&self) -> u64 {
+        self.millis / 1_000
+    }
+
+    pub(crate) const fn as_unix_millis(&self) -> u64 {
+        self.millis
+    }
+
+    pub(crate) fn checked_add(&self, dura: Duration) -> Option<SystemTime> {
+        self.millis.checked_add(dura.as_millis()).map(Self::from_unix_millis)
+    }
+
+    /// Breaks the time down into its UTC calendar fields.
+    pub(crate) fn to_calendar(self) -> CalendarTime {
+        let days = self.millis / MILLIS_PER_DAY;
+        let millis = self.millis % MILLIS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        CalendarTime {
+            year,
+            month,
+            day,
+            hour: (millis / 3_600_000) as u8,
+            minute: (millis / 60_000 % 60) as u8,
+            second: (millis / 1_000 % 60) as u8,
+            millis: (millis % 1_000) as u16,
+        }
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs).expect("SystemTime adding Duration is overflowed")
+    }
+}
+
+impl From<CalendarTime> for SystemTime {
+    fn from(time: CalendarTime) -> Self {
+        let days = days_from_civil(time.year, time.month, time.day);
+        let secs = time.hour as u64 * 3_600 + time.minute as u64 * 60 + time.second as u64;
+        Self::from_unix_millis(days * MILLIS_PER_DAY + secs * 1_000 + time.millis as u64)
+    }
+}
+
+impl fmt::Display for SystemTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} UTC", self.to_calendar())
+    }
+}
+
+/// A UTC calendar date and time of day, as kept by an RTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct CalendarTime {
+    /// Full year - [1970, 9999]
+    pub(crate) year: u16,
+    /// Month of the year - [1, 12]
+    pub(crate) month: u8,
+    /// Day of the month - [1, 31]
+    pub(crate) day: u8,
+    /// Hours after midnight - [0, 23]
+    pub(crate) hour: u8,
+    /// Minutes after the hour - [0, 59]
+    pub(crate) minute: u8,
+    /// Seconds after the minute - [0, 59]
+    pub(crate) second: u8,
+    /// Milliseconds after the second - [0, 999]
+    pub(crate) millis: u16,
+}
+
+impl CalendarTime {
+    /// Returns `None` if a field is out of range, e.g. February 30th.
+    pub(crate) fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8, millis: u16) -> Option<Self> {
+        let valid = (MIN_YEAR..=MAX_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour <= 23
+            && minute <= 59
+            && second <= 59
+            && millis <= 999;
+        valid.then_some(Self { year, month, day, hour, minute, second, millis })
+    }
+
+    /// ISO weekday, from 1 for Monday to 7 for Sunday.
+    pub(crate) fn weekday(&self) -> u8 {
+        // 1970-01-01 was a Thursday
+        ((days_from_civil(self.year, self.month, self.day) + 3) % 7 + 1) as u8
+    }
+}
+
+impl fmt::Display for CalendarTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ParseCalendarError;
+
+impl fmt::Display for ParseCalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected YYYY-MM-DD HH:MM[:SS]")
+    }
+}
+
+/// Parses `YYYY-MM-DD HH:MM[:SS]`, also with a `T` between date and time.
+impl FromStr for CalendarTime {
+    type Err = ParseCalendarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, time) = s.trim().split_once([' ', 'T']).ok_or(ParseCalendarError)?;
+
+        let mut date = date.split('-');
+        let year = parse_field(date.next())?;
+        let month = parse_field(date.next())?;
+        let day = parse_field(date.next())?;
+
+        let mut time = time.trim().split(':');
+        let hour = parse_field(time.next())?;
+        let minute = parse_field(time.next())?;
+        let second = match time.next() {
+            Some(second) => parse_field(Some(second))?,
+            None => 0,
+        };
+
+        if date.next().is_some() || time.next().is_some() {
+            return Err(ParseCalendarError);
+        }
+        CalendarTime::new(year, month, day, hour, minute, second, 0).ok_or(ParseCalendarError)
+    }
+}
+
+fn parse_field<T: FromStr>(field: Option<&str>) -> Result<T, ParseCalendarError> {
+    field
+        .filter(|field| !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|field| field.parse().ok())
+        .ok_or(ParseCalendarError)
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days between 1970-01-01 and the given date, after Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: u16, month: u8, day: u8) -> u64 {
+    let year = year as u64 - (month <= 2) as u64;
+    let era = year / 400;
+    let yoe = year % 400;
+    let month = month as u64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as u64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// The inverse of `days_from_civil`.
+fn civil_from_days(days: u64) -> (u16, u8, u8) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let doe = days % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u8;
+    let year = (yoe + era * 400 + (month <= 2) as u64) as u16;
+    (year, month, day)
+}
@@ -1,111 +1,165 @@
 use core::{future::Future, pin::pin};
-use alloc::{sync::Arc, vec::Vec};
 use futures_core::{FusedStream, Stream};
-use futures_util::{future::{select, Either}, task::AtomicWaker};
-use spin::Mutex;
-use super::{duration::Duration, instant::Instant};
+use futures_util::future::{select, Either};
+use super::{clock::{Clock, SysClock}, duration::DurationOf, instant::InstantOf, timer_queue::TimerEntry};
 
-pub(crate) static WAITING_TIMERS: Mutex<Vec<(Instant, Arc<AtomicWaker>)>> = Mutex::new(Vec::new());
+/// A timer on the system clock.
+pub(crate) type Timer = TimerOf<SysClock>;
+/// A ticker on the system clock.
+pub(crate) type Ticker = TickerOf<SysClock>;
 
-pub(crate) struct Timer {
-    expires_at: Instant,
-    waker: Arc<AtomicWaker>,
+pub(crate) struct TimerOf<C: Clock> {
+    expires_at: InstantOf<C>,
+    entry: TimerEntry<C>,
 }
 
-impl Timer {
-    pub(crate) fn at(expires_at: Instant) -> Self {
-        Self { expires_at, waker: Arc::new(AtomicWaker::new()) }
+impl<C: Clock> TimerOf<C> {
+    pub(crate) fn at(expires_at: InstantOf<C>) -> Self {
+        Self { expires_at, entry: TimerEntry::new() }
     }
 
-    pub(crate) fn after(duration: Duration) -> Self {
+    pub(crate) fn after(duration: DurationOf<C>) -> Self {
         Self {
-            expires_at: Instant::now() + duration,
-            waker: Arc::new(AtomicWaker::new()),
+            expires_at: InstantOf::now() + duration,
+            entry: TimerEntry::new(),
         }
     }
 
     pub(crate) fn after_ticks(ticks: u64) -> Self {
-        Self::after(Duration::from_ticks(ticks))
+        Self::after(DurationOf::from_ticks(ticks))
     }
 
     pub(crate) fn after_nanos(nanos: u64) -> Self {
-        Self::after(Duration::from_nanos(nanos))
+        Self::after(DurationOf::from_nanos(nanos))
     }
 
     pub(crate) fn after_micros(micros: u64) -> Self {
-        Self::after(Duration::from_micros(micros))
+        Self::after(DurationOf::from_micros(micros))
     }
 
     pub(crate) fn after_millis(millis: u64) -> Self {
-        Self::after(Duration::from_millis(millis))
+        Self::after(DurationOf::from_millis(millis))
     }
 
     pub(crate) fn after_secs(secs: u64) -> Self {
-        Self::after(Duration::from_secs(secs))
+        Self::after(DurationOf::from_secs(secs))
     }
 }
 
-impl Unpin for Timer {}
+impl<C: Clock> Unpin for TimerOf<C> {}
 
-impl Future for Timer {
+impl<C: Clock> Future for TimerOf<C> {
     type Output = ();
-    fn poll(self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Self::Output> {
-        if self.expires_at <= Instant::now() {
+    fn poll(mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Self::Output> {
+        if self.expires_at <= InstantOf::now() {
+            self.entry.deregister();
             core::task::Poll::Ready(())
         } else {
-            self.waker.register(cx.waker());
-            WAITING_TIMERS.lock().push((self.expires_at, self.waker.clone()));
+            let expires_at = self.expires_at;
+            self.entry.register(expires_at, cx.waker());
             core::task::Poll::Pending
         }
     }
 }
 
-pub(crate) struct Ticker {
-    expires_at: Instant,
-    duration: Duration,
-    waker: Arc<AtomicWaker>,
+/// How a `Ticker` catches up on ticks it could not deliver on time, e.g.
+/// because the task polling it was blocked for longer than a period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum MissedTickBehavior {
+    /// Fire the missed ticks back to back until the schedule is caught up.
+    #[default]
+    Burst,
+    /// Fire once, then schedule the next tick a full period after now.
+    Delay,
+    /// Fire once, then resume on the next tick of the original schedule.
+    Skip,
 }
 
-impl Ticker {
-    pub(crate) fn every(duration: Duration) -> Self {
+pub(crate) struct TickerOf<C: Clock> {
+    expires_at: InstantOf<C>,
+    duration: DurationOf<C>,
+    behavior: MissedTickBehavior,
+    missed: u64,
+    entry: TimerEntry<C>,
+}
+
+impl<C: Clock> TickerOf<C> {
+    /// Ticks every `duration`; a zero period is taken as one tick.
+    pub(crate) fn every(duration: DurationOf<C>) -> Self {
+        let duration = duration.max(DurationOf::from_ticks(1));
         Self {
-            expires_at: Instant::now() + duration,
+            expires_at: InstantOf::now() + duration,
             duration, 
-            waker: Arc::new(AtomicWaker::new()),
+            behavior: MissedTickBehavior::Burst,
+            missed: 0,
+            entry: TimerEntry::new(),
         }
     }
 
+    pub(crate) fn with_missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    pub(crate) fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.behavior
+    }
+
+    pub(crate) fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.behavior = behavior;
+    }
+
+    /// Ticks that were due a full period or more before they could fire.
+    ///
+    /// With `Burst` these still fire, late; otherwise they are dropped.
+    pub(crate) fn missed_ticks(&self) -> u64 {
+        self.missed
+    }
+
     pub(crate) fn reset(&mut self) {
-        self.expires_at = Instant::now() + self.duration;
+        self.expires_at = InstantOf::now() + self.duration;
     }
 
-    pub(crate) fn reset_at(&mut self, deadline: Instant) {
+    pub(crate) fn reset_at(&mut self, deadline: InstantOf<C>) {
         self.expires_at = deadline + self.duration;
     }
 
-    pub(crate) fn reset_after(&mut self, after: Duration) {
-        self.expires_at = Instant::now() + after + self.duration;
+    pub(crate) fn reset_after(&mut self, after: DurationOf<C>) {
+        self.expires_at = InstantOf::now() + after + self.duration;
     }
 }
 
-impl Unpin for Ticker {}
+impl<C: Clock> Unpin for TickerOf<C> {}
 
-impl Stream for Ticker {
+impl<C: Clock> Stream for TickerOf<C> {
     type Item = ();
     fn poll_next(mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>) -> core::task::Poll<Option<Self::Item>> {
-        if self.expires_at <= Instant::now() {
+        let now = InstantOf::now();
+        if self.expires_at <= now {
             let dur = self.duration;
-            self.expires_at += dur;
+            let behind = (now - self.expires_at).as_ticks() / dur.as_ticks();
+            // a bursting ticker still fires its late ticks, so count each one as it does
+            self.missed += match self.behavior {
+                MissedTickBehavior::Burst => (behind > 0) as u64,
+                _ => behind,
+            };
+            self.expires_at = match self.behavior {
+                MissedTickBehavior::Burst => self.expires_at + dur,
+                MissedTickBehavior::Delay => now + dur,
+                MissedTickBehavior::Skip => self.expires_at + dur * (behind + 1),
+            };
+            // the deadline is used up; a bursting ticker may not be polled until its next one
+            self.entry.deregister();
             core::task::Poll::Ready(Some(()))
         } else {
-            self.waker.register(cx.waker());
-            WAITING_TIMERS.lock().push((self.expires_at, self.waker.clone()));
+            let expires_at = self.expires_at;
+            self.entry.register(expires_at, cx.waker());
             core::task::Poll::Pending
         }
     }
 }
 
-impl FusedStream for Ticker {
+impl<C: Clock> FusedStream for TickerOf<C> {
     fn is_terminated(&self) -> bool {
         false
     }
@@ -114,16 +168,16 @@ impl FusedStream for Ticker {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct TimeoutError;
 
-pub(crate) async fn with_timeout<F: Future>(timeout: Duration, fut: F) -> Result<F::Output, TimeoutError> {
-    let timer = Timer::after(timeout);
+pub(crate) async fn with_timeout<C: Clock, F: Future>(timeout: DurationOf<C>, fut: F) -> Result<F::Output, TimeoutError> {
+    let timer = TimerOf::after(timeout);
     match select(pin!(fut), timer).await {
         Either::Left((r, _)) => Ok(r),
         Either::Right(_) => Err(TimeoutError),
     }
 }
 
-pub(crate) async fn with_deadline<F: Future>(deadline: Instant, fut: F) -> Result<F::Output, TimeoutError> {
-    let timer = Timer::at(deadline);
+pub(crate) async fn with_deadline<C: Clock, F: Future>(deadline: InstantOf<C>, fut: F) -> Result<F::Output, TimeoutError> {
+    let timer = TimerOf::at(deadline);
     match select(pin!(fut), timer).await {
         Either::Left((r, _)) => Ok(r),
         Either::Right(_) => Err(TimeoutError),
@@ -133,19 +187,19 @@ pub(crate) async fn with_deadline<F: Future>(deadline: Instant, fut: F) -> Resul
 pub(crate) trait WithTimeout {
     type Output;
 
-    async fn with_timeout(self, timeout: Duration) -> Result<Self::Output, TimeoutError>;
+    async fn with_timeout<C: Clock>(self, timeout: DurationOf<C>) -> Result<Self::Output, TimeoutError>;
 
-    async fn with_deadline(self, deadline: Instant) -> Result<Self::Output, TimeoutError>;
+    async fn with_deadline<C: Clock>(self, deadline: InstantOf<C>) -> Result<Self::Output, TimeoutError>;
 }
 
 impl<F: Future> WithTimeout for F {
     type Output = F::Output;
 
-    async fn with_timeout(self, timeout: Duration) -> Result<Self::Output, TimeoutError> {
+    async fn with_timeout<C: Clock>(self, timeout: DurationOf<C>) -> Result<Self::Output, TimeoutError> {
         with_timeout(timeout, self).await
     }
 
-    async fn with_deadline(self, deadline: Instant) -> Result<Self::Output, TimeoutError> {
+    async fn with_deadline<C: Clock>(self, deadline: InstantOf<C>) -> Result<Self::Output, TimeoutError> {
         with_deadline(deadline, self).await
     }
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+use core::{sync::atomic::{AtomicU32, Ordering}, task::Waker};
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use spin::Mutex;
+
+use super::{clock::{Clock, SysClock}, instant::InstantOf};
+
+pub(crate) static TIMER_QUEUE: Mutex<TimerQueue<SysClock>> = Mutex::new(TimerQueue::new());
+
+type TimerKey<C> = (InstantOf<C>, u32);
+
+/// Pending timers ordered by deadline.
+///
+/// Each timer owns at most one entry, keyed by its deadline and a unique id so
+/// that it can be removed exactly when the timer is dropped or re-armed. The
+/// earliest deadline is cached for the idle loop and handed to the clock's
+/// alarm.
+pub(crate) struct TimerQueue<C: Clock> {
+    timers: BTreeMap<TimerKey<C>, Waker>,
+    next: Option<InstantOf<C>>,
+}
+
+impl<C: Clock> TimerQueue<C> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            timers: BTreeMap::new(),
+            next: None,
+        }
+    }
+
+    fn insert(&mut self, key: TimerKey<C>, waker: Waker) {
+        self.timers.insert(key, waker);
+        self.update_next();
+    }
+
+    fn remove(&mut self, key: &TimerKey<C>) {
+        self.timers.remove(key);
+        self.update_next();
+    }
+
+    fn update_next(&mut self) {
+        self.next = self.timers.first_key_value().map(|((deadline, _), _)| *deadline);
+        C::set_alarm(self.next.map(|deadline| deadline.as_ticks()));
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<InstantOf<C>> {
+        self.next
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Removes every timer due at `now`, returning their wakers to be woken once the queue is unlocked.
+    pub(crate) fn take_expired(&mut self, now: InstantOf<C>) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while self.next.is_some_and(|deadline| deadline <= now) {
+            if let Some((_, waker)) = self.timers.pop_first() {
+                wakers.push(waker);
+            }
+            self.next = self.timers.first_key_value().map(|((deadline, _), _)| *deadline);
+        }
+        // re-arms the alarm even if nothing expired, e.g. when it fired early
+        self.update_next();
+        wakers
+    }
+}
+
+/// A timer's registration in its clock's queue, removed again on drop.
+pub(crate) struct TimerEntry<C: Clock> {
+    id: u32,
+    deadline: Option<InstantOf<C>>,
+}
+
+impl<C: Clock> TimerEntry<C> {
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            deadline: None,
+        }
+    }
+
+    /// Arms the entry for `deadline`, replacing any earlier registration.
+    pub(crate) fn register(&mut self, deadline: InstantOf<C>, waker: &Waker) {
+        let mut queue = C::timer_queue().lock();
+        if let Some(old) = self.deadline {
+            if old == deadline {
+                if let Some(registered) = queue.timers.get_mut(&(old, self.id)) {
+                    if !registered.will_wake(waker) {
+                        *registered = waker.clone();
+                    }
+                    return;
+                }
+            } else {
+                queue.remove(&(old, self.id));
+            }
+        }
+        queue.insert((deadline, self.id), waker.clone());
+        self.deadline = Some(deadline);
+    }
+
+    pub(crate) fn deregister(&mut self) {
+        if let Some(deadline) = self.deadline.take() {
+            C::timer_queue().lock().remove(&(deadline, self.id));
+        }
+    }
+}
+
+impl<C: Clock> Drop for TimerEntry<C> {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
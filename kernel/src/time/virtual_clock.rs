@@ -0,0 +1,47 @@
+use core::future::Future;
+
+use alloc::boxed::Box;
+
+use crate::task::{executor::Executor, idle::IdleHook, join_handle::JoinError};
+use super::{advance_sys_ticks_to, clock::SysClock, hires::{self, advance_hires_ticks_to, HiResClock}, instant::Instant};
+
+/// Idle hook that, instead of sleeping, jumps both clocks straight to the next timer deadline.
+///
+/// With it, code built on `Timer`, `Ticker` or `with_timeout` runs without
+/// waiting for real time, and every run sees the same sequence of instants.
+/// Nothing else may advance either clock meanwhile, so the SysTick (or the
+/// simulator's interrupt thread) must not be running and the high-resolution
+/// counter must stand still, as the simulator's does until it is booted.
+pub(crate) struct VirtualClock;
+
+impl IdleHook for VirtualClock {
+    fn sleep(&self, deadline: Option<Instant>) {
+        // a high-resolution deadline is met on the system clock's next tick at the latest
+        let hires = hires::next_deadline().map(|hires| {
+            (hires, Instant::from_ticks(hires.to_clock::<SysClock>().as_ticks() + 1))
+        });
+        match (deadline, hires) {
+            (Some(deadline), hires) if hires.is_none_or(|(_, after)| deadline < after) => {
+                advance_sys_ticks_to(deadline.as_ticks());
+                advance_hires_ticks_to(deadline.to_clock::<HiResClock>().as_ticks());
+            }
+            (_, Some((hires, _))) => {
+                advance_hires_ticks_to(hires.as_ticks());
+                advance_sys_ticks_to(hires.to_clock::<SysClock>().as_ticks());
+            }
+            _ => panic!("virtual clock stalled: no task is ready and no timer is pending"),
+        }
+    }
+}
+
+/// Runs `future` to completion on a fresh executor driven by `VirtualClock`.
+///
+/// Tasks it spawns share that executor. Panics if every task ends up waiting
+/// on something other than a timer, since nothing could ever wake them.
+pub(crate) fn run_virtual<F>(future: F) -> Result<F::Output, JoinError>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    Executor::with_idle_hook(Box::new(VirtualClock)).block_on(future)
+}